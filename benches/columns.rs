@@ -0,0 +1,64 @@
+//! Column access on a 1000 x 2000 alignment, row-major (`MSA::columns`)
+//! against a single transpose (`MSA::to_columns`), and the column analyses
+//! built on the transpose. Run with `cargo bench --bench columns`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use align_rs::msa::MSA;
+use align_rs::record::Record;
+
+const ROWS: usize = 1000;
+const COLS: usize = 2000;
+const RUNS: usize = 5;
+
+/// Deterministic alignment of `rows` records of `cols` residues and gaps
+fn alignment(rows: usize, cols: usize) -> MSA {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let records = (0..rows)
+        .map(|i| {
+            let sequence: String = (0..cols)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    b"ACGT-"[(state % 5) as usize] as char
+                })
+                .collect();
+            Record::new(&format!("seq{}", i), &sequence)
+        })
+        .collect();
+    MSA::new(records, HashMap::new(), HashMap::new())
+}
+
+/// Best wall time over `RUNS` runs of `f`
+fn time<T>(name: &str, mut f: impl FnMut() -> T) -> T {
+    let mut best = Duration::MAX;
+    let mut out = f();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        out = f();
+        best = best.min(start.elapsed());
+    }
+    println!("{:<24} {:>10.3} ms", name, best.as_secs_f64() * 1e3);
+    out
+}
+
+fn main() {
+    let msa = alignment(ROWS, COLS);
+    println!("{} records x {} columns, best of {}", ROWS, COLS, RUNS);
+
+    let row_major = time("columns (row-major)", || {
+        msa.columns().map(|x| x.len()).sum::<usize>()
+    });
+    let transposed = time("to_columns", || {
+        msa.to_columns().iter().map(|x| x.len()).sum::<usize>()
+    });
+    assert_eq!(row_major, transposed);
+
+    let profile = time("profile", || msa.profile());
+    let gaps = time("gap_fractions", || msa.gap_fractions());
+    let entropy = time("column_entropy", || msa.column_entropy(false));
+    assert_eq!(profile.len(), COLS);
+    assert_eq!(gaps.len(), COLS);
+    assert_eq!(entropy.len(), COLS);
+}
@@ -0,0 +1,118 @@
+use core::fmt;
+
+/// IUPAC nucleotide ambiguity codes, shared by DNA and RNA
+const AMBIGUITY_CODES: &[u8] = b"RYKMSWBDHVN";
+
+/// Residues of protein sequences, including ambiguity codes, the rare
+/// amino acids and the stop symbol
+const AMINO_ACIDS: &[u8] = b"ACDEFGHIKLMNPQRSTVWYBZJXUO*";
+
+/// Kind of residues found in an alignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// Nucleotides `ACGT` with IUPAC ambiguity codes
+    Dna,
+
+    /// Nucleotides `ACGU` with IUPAC ambiguity codes
+    Rna,
+
+    /// Amino acids with `B`, `Z`, `J`, `X`, `U`, `O` and the stop `*`
+    Protein,
+
+    /// No residue to classify, every character is accepted
+    Unknown,
+}
+
+impl Alphabet {
+    /// Returns `true` if `residue` (case insensitive) belongs to the
+    /// alphabet. Gaps are not residues and are handled by the callers.
+    /// # Example
+    /// ```
+    /// use align_rs::alphabet::Alphabet;
+    ///
+    /// assert!(Alphabet::Dna.contains(b'r'));
+    /// assert!(!Alphabet::Dna.contains(b'U'));
+    /// assert!(Alphabet::Protein.contains(b'W'));
+    /// ```
+    pub fn contains(&self, residue: u8) -> bool {
+        let residue = residue.to_ascii_uppercase();
+        match self {
+            Alphabet::Dna => b"ACGT".contains(&residue) || AMBIGUITY_CODES.contains(&residue),
+            Alphabet::Rna => b"ACGU".contains(&residue) || AMBIGUITY_CODES.contains(&residue),
+            Alphabet::Protein => AMINO_ACIDS.contains(&residue),
+            Alphabet::Unknown => true,
+        }
+    }
+
+    /// Classifies `residues` (gaps excluded). They are nucleotides if at
+    /// least 90% are `A`, `C`, `G`, `T`, `U` or `N` and all are valid
+    /// nucleotide codes; RNA if `U` outnumbers `T`, DNA otherwise.
+    /// Anything else is protein, and no residue at all is `Unknown`.
+    pub(crate) fn guess<I>(residues: I) -> Alphabet
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let (mut total, mut core, mut t, mut u) = (0usize, 0usize, 0usize, 0usize);
+        let mut nucleotides = true;
+        for residue in residues {
+            let residue = residue.to_ascii_uppercase();
+            total += 1;
+            match residue {
+                b'A' | b'C' | b'G' | b'N' => core += 1,
+                b'T' => t += 1,
+                b'U' => u += 1,
+                _ if AMBIGUITY_CODES.contains(&residue) => {}
+                _ => nucleotides = false,
+            }
+        }
+        if total == 0 {
+            Alphabet::Unknown
+        } else if nucleotides && (core + t + u) * 10 >= total * 9 {
+            if u > t {
+                Alphabet::Rna
+            } else {
+                Alphabet::Dna
+            }
+        } else {
+            Alphabet::Protein
+        }
+    }
+}
+
+impl fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Alphabet::Dna => "dna",
+            Alphabet::Rna => "rna",
+            Alphabet::Protein => "protein",
+            Alphabet::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess() {
+        assert_eq!(
+            Alphabet::guess(b"ACGTACGTRN".iter().copied()),
+            Alphabet::Dna
+        );
+        assert_eq!(Alphabet::guess(b"acguacgu".iter().copied()), Alphabet::Rna);
+        assert_eq!(Alphabet::guess(b"ACGTUUU".iter().copied()), Alphabet::Rna);
+        assert_eq!(Alphabet::guess(b"ACGTTU".iter().copied()), Alphabet::Dna);
+        assert_eq!(
+            Alphabet::guess(b"MKVLAAGIVA".iter().copied()),
+            Alphabet::Protein
+        );
+        assert_eq!(
+            Alphabet::guess(b"ACGTKMSWRY".iter().copied()),
+            Alphabet::Protein
+        );
+        assert_eq!(Alphabet::guess(Vec::new()), Alphabet::Unknown);
+        assert_eq!(Alphabet::Rna.to_string(), "rna");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::msa::GapSet;
+
+/// Nucleotide order of the NCBI amino-acid strings, for each codon position
+const BASES: &[u8; 4] = b"TCAG";
+
+/// Amino acids of the 64 codons, in NCBI order (TTT, TTC, TTA, ..., GGG)
+const STANDARD: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const VERTEBRATE_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG";
+
+/// Genetic code mapping codons to amino acids, stop codons being `*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodonTable {
+    /// NCBI translation table number
+    id: u8,
+
+    /// Amino acid of every codon, in NCBI order
+    amino_acids: [u8; 64],
+}
+
+impl CodonTable {
+    /// Creates the standard genetic code (NCBI table 1)
+    pub fn standard() -> Self {
+        CodonTable {
+            id: 1,
+            amino_acids: *STANDARD,
+        }
+    }
+
+    /// Creates the genetic code with NCBI number `id`, if supported. Tables
+    /// 1 (standard), 2 (vertebrate mitochondrial) and 11 (bacterial, archaeal
+    /// and plant plastid) are available.
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    ///
+    /// let table = CodonTable::from_ncbi(2).unwrap();
+    /// assert_eq!(table.translate(b"AGA"), b'*');
+    /// assert!(CodonTable::from_ncbi(7).is_none());
+    /// ```
+    pub fn from_ncbi(id: u8) -> Option<Self> {
+        let amino_acids = match id {
+            1 | 11 => STANDARD,
+            2 => VERTEBRATE_MITOCHONDRIAL,
+            _ => return None,
+        };
+        Some(CodonTable {
+            id,
+            amino_acids: *amino_acids,
+        })
+    }
+
+    /// Returns the NCBI number of the table
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the amino acid encoded by `codon` (case insensitive, `U` read
+    /// as `T`): `*` for a stop codon, `-` for a codon made only of gaps and
+    /// `X` for anything else that is not three unambiguous nucleotides.
+    pub fn translate(&self, codon: &[u8]) -> u8 {
+        self.translate_with(codon, &GapSet::default())
+    }
+
+    /// Returns the amino acid encoded by `codon` like
+    /// [`CodonTable::translate`], a codon made only of characters of `gaps`
+    /// giving `-`
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    /// use align_rs::msa::GapSet;
+    ///
+    /// let table = CodonTable::standard();
+    /// assert_eq!(table.translate_with(b"~~~", &GapSet::new(&['~'])), b'-');
+    /// assert_eq!(table.translate(b"~~~"), b'X');
+    /// ```
+    pub fn translate_with(&self, codon: &[u8], gaps: &GapSet) -> u8 {
+        if !codon.is_empty() && codon.iter().all(|b| gaps.is_gap(b)) {
+            return b'-';
+        }
+        if codon.len() != 3 {
+            return b'X';
+        }
+        let mut index = 0;
+        for &b in codon {
+            let b = match b.to_ascii_uppercase() {
+                b'U' => b'T',
+                b => b,
+            };
+            match BASES.iter().position(|&x| x == b) {
+                Some(i) => index = index * 4 + i,
+                None => return b'X',
+            }
+        }
+        self.amino_acids[index]
+    }
+
+    /// Returns `true` if `codon` is a stop codon
+    pub fn is_stop(&self, codon: &[u8]) -> bool {
+        self.translate(codon) == b'*'
+    }
+}
+
+impl Default for CodonTable {
+    fn default() -> Self {
+        CodonTable::standard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let table = CodonTable::standard();
+        assert_eq!(table.translate(b"ATG"), b'M');
+        assert_eq!(table.translate(b"ggc"), b'G');
+        assert_eq!(table.translate(b"UGG"), b'W');
+        assert_eq!(table.translate(b"---"), b'-');
+        assert_eq!(table.translate(b"A-G"), b'X');
+        assert_eq!(table.translate(b"ANG"), b'X');
+        assert_eq!(table.translate(b"AT"), b'X');
+        assert!(table.is_stop(b"TAA"));
+        assert!(table.is_stop(b"TGA"));
+        assert!(!CodonTable::from_ncbi(2).unwrap().is_stop(b"TGA"));
+        assert_eq!(CodonTable::from_ncbi(11).unwrap().id(), 11);
+    }
+}
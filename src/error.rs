@@ -0,0 +1,180 @@
+use std::fmt;
+use std::io;
+
+/// Alternative name of [`Error`], for code importing several error types
+/// # Example
+/// ```
+/// use align_rs::error::AlignError;
+/// use align_rs::reader::Reader;
+///
+/// match Reader::new(&b"2 4\nseq1 ACGT\nseq2 ACG\n"[..]).read_phylip() {
+///     Err(AlignError::UnequalLength { id, expected, found }) => {
+///         assert_eq!((id.as_str(), expected, found), ("seq2", 4, 3))
+///     }
+///     other => panic!("unexpected result {:?}", other),
+/// }
+/// ```
+pub type AlignError = Error;
+
+/// Errors returned when reading or transforming an alignment
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying I/O failure
+    Io(io::Error),
+
+    /// The first line is not a header of the expected format
+    InvalidHeader { found: String },
+
+    /// A record does not have as many columns as the alignment
+    UnequalLength {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    /// A column annotation does not have as many columns as the alignment
+    UnequalAnnotationLength {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    /// A character column annotation holds a non-ASCII character, at the
+    /// given 0-based byte column
+    NonAsciiAnnotation {
+        name: String,
+        column: usize,
+        value: char,
+    },
+
+    /// A record holds a character outside the expected alphabet, at the
+    /// given 0-based column
+    InvalidResidue {
+        id: String,
+        column: usize,
+        residue: char,
+    },
+
+    /// A line could not be parsed
+    ParseError { line: usize, message: String },
+
+    /// No record with the given ID in the alignment
+    RecordNotFound { id: String },
+
+    /// A 0-based column index beyond the last column of the alignment
+    ColumnOutOfRange { column: usize, len: usize },
+
+    /// A record cannot be reconciled with its counterpart in another alignment
+    IncompatibleRecord { id: String, message: String },
+
+    /// A record order does not match the records of the alignment: listed
+    /// IDs with no record, IDs listed more than once and records not listed
+    InvalidOrder {
+        missing: Vec<String>,
+        duplicated: Vec<String>,
+        unlisted: Vec<String>,
+    },
+
+    /// Error while reading the file at `path`
+    InFile { path: String, error: Box<Error> },
+
+    /// A coding sequence is out of frame at the given 0-based codon
+    BrokenReadingFrame {
+        id: String,
+        codon: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::InvalidHeader { found } => write!(f, "Unrecognised header: {}", found),
+            Error::UnequalLength {
+                id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Sequence {} has length {} but alignment has {} columns",
+                id, found, expected
+            ),
+            Error::UnequalAnnotationLength {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Column annotation {} has length {} but alignment has {} columns",
+                name, found, expected
+            ),
+            Error::NonAsciiAnnotation {
+                name,
+                column,
+                value,
+            } => write!(
+                f,
+                "Column annotation {} has non-ASCII character {:?} at column {}",
+                name, value, column
+            ),
+            Error::InvalidResidue {
+                id,
+                column,
+                residue,
+            } => write!(
+                f,
+                "Sequence {} has invalid residue {:?} at column {}",
+                id, residue, column
+            ),
+            Error::ParseError { line, message } => write!(f, "Line {}: {}", line, message),
+            Error::RecordNotFound { id } => write!(f, "No record with ID {}", id),
+            Error::ColumnOutOfRange { column, len } => write!(
+                f,
+                "Column {} is out of range for an alignment of {} columns",
+                column, len
+            ),
+            Error::IncompatibleRecord { id, message } => {
+                write!(f, "Record {} is incompatible: {}", id, message)
+            }
+            Error::InFile { path, error } => write!(f, "{}: {}", path, error),
+            Error::InvalidOrder {
+                missing,
+                duplicated,
+                unlisted,
+            } => {
+                write!(f, "Invalid record order")?;
+                let lists = [
+                    ("missing", missing),
+                    ("duplicated", duplicated),
+                    ("unlisted", unlisted),
+                ];
+                for (name, ids) in lists.iter().filter(|(_, ids)| !ids.is_empty()) {
+                    write!(f, ", {}: {}", name, ids.join(" "))?;
+                }
+                Ok(())
+            }
+            Error::BrokenReadingFrame { id, codon, message } => write!(
+                f,
+                "Reading frame of {} is broken at codon {}: {}",
+                id, codon, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::InFile { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -1,3 +1,10 @@
+pub mod alphabet;
+pub mod codon;
+pub mod error;
 pub mod msa;
+pub mod pairwise;
 pub mod reader;
-mod record;
+pub mod record;
+pub mod scoring;
+mod trace;
+pub mod writer;
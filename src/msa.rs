@@ -1,11 +1,44 @@
 use core::fmt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Index, Range};
 
+use crate::alphabet::Alphabet;
+use crate::codon::CodonTable;
+use crate::error::Error;
+use crate::pairwise::global_align;
 use crate::record::Record;
+use crate::scoring::ScoringMatrix;
+use crate::trace::{self, Operation};
+
+/// Characters treated as gaps unless changed with [`MSA::set_gaps`]
+const GAP_CHARS: [u8; 2] = [b'-', b'.'];
+
+/// Gap characters rewritten by [`MSA::normalize_gaps`]
+const NORMALIZED_GAP_CHARS: [char; 3] = ['-', '.', '~'];
+
+/// Amino-acid groups of strongly similar residues, marked `:` in CLUSTAL
+/// conservation lines
+pub const STRONG_GROUPS: [&str; 9] = [
+    "STA", "NEQK", "NHQK", "NDEQ", "QHRK", "MILV", "MILF", "HY", "FYW",
+];
+
+/// Amino-acid groups of weakly similar residues, marked `.` in CLUSTAL
+/// conservation lines
+pub const WEAK_GROUPS: [&str; 11] = [
+    "CSA", "ATV", "SAG", "STNK", "STPA", "SGND", "SNDEQK", "NDEQHK", "NEQHRK", "FVLIM", "HFY",
+];
+
+/// Number of records sampled by [`MSA::stats`] for the mean pairwise identity
+const STATS_IDENTITY_SAMPLE: usize = 1000;
 
 /// Structure containing multiple sequence alignments
 ///
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedMSA")
+)]
 pub struct MSA {
     /// A list of Record objects, whose sequences are all the same length
     records: Vec<Record>,
@@ -15,6 +48,44 @@ pub struct MSA {
 
     /// Per column annotation
     column_annotations: HashMap<String, String>,
+
+    /// Per column numeric annotation, `NaN` for a missing value
+    #[cfg_attr(feature = "serde", serde(default))]
+    column_scores: HashMap<String, Vec<f64>>,
+
+    /// Characters counted as gaps by the analyses of the alignment
+    #[cfg_attr(feature = "serde", serde(default))]
+    gaps: GapSet,
+}
+
+/// Deserialized fields of an [`MSA`], validated before building it
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedMSA {
+    records: Vec<Record>,
+    annotations: HashMap<String, String>,
+    column_annotations: HashMap<String, String>,
+    #[serde(default)]
+    column_scores: HashMap<String, Vec<f64>>,
+    #[serde(default)]
+    gaps: GapSet,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedMSA> for MSA {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedMSA) -> Result<Self, Self::Error> {
+        let msa = MSA {
+            records: unchecked.records,
+            annotations: unchecked.annotations,
+            column_annotations: unchecked.column_annotations,
+            column_scores: unchecked.column_scores,
+            gaps: unchecked.gaps,
+        };
+        msa.validate()?;
+        Ok(msa)
+    }
 }
 
 impl MSA {
@@ -28,9 +99,36 @@ impl MSA {
             records,
             annotations,
             column_annotations,
+            column_scores: HashMap::new(),
+            gaps: GapSet::default(),
         }
     }
 
+    /// Returns the characters counted as gaps, `-` and `.` unless changed
+    /// with [`MSA::set_gaps`]
+    pub fn gaps(&self) -> &GapSet {
+        &self.gaps
+    }
+
+    /// Sets the characters counted as gaps by every gap-aware analysis of
+    /// the alignment: gap fractions, identities, conservation, entropies,
+    /// trimming and the like. Records are left unchanged.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::{GapSet, MSA};
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "~AC-");
+    /// msa.push_record("id2", "~ACG");
+    /// msa.set_gaps(GapSet::new(&['-', '~']));
+    /// assert_eq!(msa.gap_fractions(), vec![1.0, 0.0, 0.0, 0.5]);
+    /// msa.remove_gap_columns();
+    /// assert_eq!(msa.get(0).unwrap().sequence(), "AC-");
+    /// ```
+    pub fn set_gaps(&mut self, gaps: GapSet) {
+        self.gaps = gaps;
+    }
+
     /// Returns the number of record in alignment
     /// # Example
     /// ```
@@ -55,6 +153,7 @@ impl MSA {
         self.records.clear();
         self.annotations.clear();
         self.column_annotations.clear();
+        self.column_scores.clear();
     }
 
     pub fn get_annotation(&self, name: &str) -> Option<&String> {
@@ -72,6 +171,62 @@ impl MSA {
             .push_str(value);
     }
 
+    /// Sets the column annotation `name` to `track`, replacing any previous
+    /// track of that name. Character tracks are also returned by
+    /// [`MSA::get_column_annotation`]. Fails if the track does not have one
+    /// value per column, or if a character track is not ASCII.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::{ColumnTrack, MSA};
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.set_column_track("pp", ColumnTrack::Scores(vec![0.9, 0.4])).unwrap();
+    /// assert_eq!(msa.column_track("pp"), Some(ColumnTrack::Scores(vec![0.9, 0.4])));
+    /// assert!(msa.set_column_track("cons", ColumnTrack::Chars("*".to_string())).is_err());
+    /// ```
+    pub fn set_column_track(&mut self, name: &str, track: ColumnTrack) -> Result<(), Error> {
+        if let ColumnTrack::Chars(value) = &track {
+            if let Some((column, value)) = value.char_indices().find(|(_, c)| !c.is_ascii()) {
+                return Err(Error::NonAsciiAnnotation {
+                    name: name.to_string(),
+                    column,
+                    value,
+                });
+            }
+        }
+        if track.len() != self.col_len() {
+            return Err(Error::UnequalAnnotationLength {
+                name: name.to_string(),
+                expected: self.col_len(),
+                found: track.len(),
+            });
+        }
+        self.column_annotations.remove(name);
+        self.column_scores.remove(name);
+        match track {
+            ColumnTrack::Chars(value) => {
+                self.column_annotations.insert(name.to_string(), value);
+            }
+            ColumnTrack::Scores(values) => {
+                self.column_scores.insert(name.to_string(), values);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the column annotation `name`, characters or scores
+    pub fn column_track(&self, name: &str) -> Option<ColumnTrack> {
+        match self.column_annotations.get(name) {
+            Some(value) => Some(ColumnTrack::Chars(value.clone())),
+            None => self
+                .column_scores
+                .get(name)
+                .cloned()
+                .map(ColumnTrack::Scores),
+        }
+    }
+
     pub fn add_annotation(&mut self, name: String, value: String) -> Option<String> {
         self.annotations.insert(name, value)
     }
@@ -92,115 +247,4696 @@ impl MSA {
             self.records.push(Record::new(id, seq));
         }
     }
-}
 
-impl fmt::Display for MSA {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.is_empty() {
-            return write!(f, "No sequence in alignment");
+    /// Appends `seq` like [`MSA::push_record`], but only if the record then
+    /// has the same length as the other records of the alignment. The
+    /// alignment is left unchanged on error.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.try_push_record("id1", "ACGT").unwrap();
+    /// assert!(msa.try_push_record("id2", "AC").is_err());
+    /// assert_eq!(msa.len(), 1);
+    /// ```
+    pub fn try_push_record(&mut self, id: &str, seq: &str) -> Result<(), Error> {
+        let current = self.get_record(id).map(|x| x.len()).unwrap_or(0);
+        let expected = self.records.iter().find(|x| x.id() != id).map(|x| x.len());
+        match expected {
+            Some(expected) if expected != current + seq.len() => Err(Error::UnequalLength {
+                id: id.to_string(),
+                expected,
+                found: current + seq.len(),
+            }),
+            _ => {
+                self.push_record(id, seq);
+                Ok(())
+            }
         }
+    }
 
-        let num_rows = self.len();
-        let num_cols = self.col_len();
-        let mut string = String::new();
-        let mut to_continue: &str = "";
-        for (idx, record) in self.records.iter().enumerate() {
-            if idx > 9 {
-                to_continue = "...";
-                break;
+    /// Appends the columns of `other` to the right of the alignment, matching
+    /// records by ID. Records present on one side only are padded with `-`
+    /// for the columns of the other side; those of `other` are added after
+    /// the existing ones. Column annotations are concatenated by name, padded
+    /// with spaces where one side lacks them, and alignment annotations of
+    /// `other` are only added if absent. An alignment without records counts
+    /// as having no columns, so `col_len()` ends up as the sum of both.
+    /// See [`MSA::join`] to reject records missing from one side instead.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut gene1 = MSA::default();
+    /// gene1.push_record("sp1", "ACG");
+    /// gene1.push_record("sp2", "ACT");
+    /// let mut gene2 = MSA::default();
+    /// gene2.push_record("sp2", "GG");
+    /// gene2.push_record("sp3", "GC");
+    /// gene1.concat(&gene2);
+    /// assert_eq!(
+    ///     gene1.to_string(),
+    ///     "Alignment with 3 rows and 5 columns\nsp1\tACG--\nsp2\tACTGG\nsp3\t---GC\n"
+    /// );
+    /// ```
+    pub fn concat(&mut self, other: &MSA) {
+        let op = Operation::start("concat");
+        let (left, right) = (self.col_len(), other.col_len());
+        for record in &mut self.records {
+            match other.get_record(record.id()) {
+                Some(x) => record.push_seq(x.sequence()),
+                None => record.push_seq(&"-".repeat(right)),
             }
-            let truncated_sequence = &record.sequence()[..std::cmp::min(30, record.len())];
-            string.push_str(&format!(
-                "{}\t{}\n",
-                record.id(),
-                if record.len() > 30 {
-                    format!("{}...", truncated_sequence)
-                } else {
-                    truncated_sequence.to_string()
-                }
-            ));
         }
-        let header = if num_rows == 1 {
-            format!("Alignment with {} row", num_rows)
-        } else {
-            format!("Alignment with {} rows", num_rows)
+        for record in &other.records {
+            if !self.contains(record.id()) {
+                let sequence = format!("{}{}", "-".repeat(left), record.sequence());
+                self.records.push(record.with_sequence(sequence));
+            }
+        }
+
+        for (name, value) in &mut self.column_annotations {
+            match other.column_annotations.get(name) {
+                Some(x) => value.push_str(x),
+                None => value.push_str(&" ".repeat(right)),
+            }
+        }
+        for (name, value) in &other.column_annotations {
+            self.column_annotations
+                .entry(name.clone())
+                .or_insert_with(|| format!("{}{}", " ".repeat(left), value));
+        }
+        for (name, values) in &mut self.column_scores {
+            match other.column_scores.get(name) {
+                Some(x) => values.extend(x),
+                None => values.resize(left + right, f64::NAN),
+            }
+        }
+        for (name, values) in &other.column_scores {
+            self.column_scores.entry(name.clone()).or_insert_with(|| {
+                let mut padded = vec![f64::NAN; left];
+                padded.extend(values);
+                padded
+            });
+        }
+        for (name, value) in &other.annotations {
+            self.annotations
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        op.finish(self);
+    }
+
+    /// Returns a new alignment with the columns of `other` appended to the
+    /// right, matching records by ID like [`MSA::concat`]. Unless
+    /// `fill_missing` is set, a record ID present in only one of the two
+    /// alignments is an error; with it, the missing side is filled with `-`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut gene1 = MSA::default();
+    /// gene1.push_record("sp1", "ACG");
+    /// let mut gene2 = MSA::default();
+    /// gene2.push_record("sp2", "GG");
+    /// assert!(gene1.join(&gene2, false).is_err());
+    /// assert_eq!(gene1.join(&gene2, true).unwrap().col_len(), 5);
+    /// ```
+    pub fn join(&self, other: &MSA, fill_missing: bool) -> Result<MSA, Error> {
+        if !fill_missing {
+            let missing = self
+                .records
+                .iter()
+                .find(|x| !other.contains(x.id()))
+                .or_else(|| other.records.iter().find(|x| !self.contains(x.id())));
+            if let Some(record) = missing {
+                return Err(Error::RecordNotFound {
+                    id: record.id().to_string(),
+                });
+            }
+        }
+        let mut joined = self.clone();
+        joined.concat(other);
+        Ok(joined)
+    }
+
+    /// Appends the records of `other` below those of the alignment. Both
+    /// must have the same number of columns, unless one has no records.
+    /// Annotations and column annotations of `other` are only added where
+    /// the alignment has none with the same name.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACG");
+    /// let mut other = MSA::default();
+    /// other.push_record("id2", "A-G");
+    /// msa.extend(other).unwrap();
+    /// assert_eq!(msa.len(), 2);
+    /// ```
+    pub fn extend(&mut self, other: MSA) -> Result<(), Error> {
+        if !self.is_empty() && !other.is_empty() && self.col_len() != other.col_len() {
+            return Err(Error::UnequalLength {
+                id: other.records[0].id().to_string(),
+                expected: self.col_len(),
+                found: other.col_len(),
+            });
+        }
+        self.records.extend(other.records);
+        for (name, value) in other.annotations {
+            self.annotations.entry(name).or_insert(value);
+        }
+        for (name, value) in other.column_annotations {
+            self.column_annotations.entry(name).or_insert(value);
+        }
+        for (name, values) in other.column_scores {
+            self.column_scores.entry(name).or_insert(values);
+        }
+        Ok(())
+    }
+
+    /// Returns a new alignment holding the records named in `ids`, in the
+    /// order given, along with the IDs that were not found. Annotations and
+    /// column annotations are carried over unchanged.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC-T");
+    /// let (subset, missing) = msa.select(&["id2", "id3"]);
+    /// assert_eq!(subset.len(), 1);
+    /// assert_eq!(missing, vec!["id3".to_string()]);
+    /// ```
+    pub fn select(&self, ids: &[&str]) -> (MSA, Vec<String>) {
+        let mut missing = Vec::new();
+        let mut records = Vec::with_capacity(ids.len());
+        for &id in ids {
+            match self.records.iter().find(|x| x.id() == id) {
+                Some(record) => records.push(record.clone()),
+                None => missing.push(id.to_string()),
+            }
+        }
+        let msa = MSA {
+            records,
+            annotations: self.annotations.clone(),
+            column_annotations: self.column_annotations.clone(),
+            column_scores: self.column_scores.clone(),
+            gaps: self.gaps.clone(),
         };
+        (msa, missing)
+    }
 
-        let column_desc = if num_cols == 1 {
-            format!(" and {} column", num_cols)
-        } else {
-            format!(" and {} columns", num_cols)
+    /// Keeps only the records for which `f` returns `true`, in place
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Record) -> bool,
+    {
+        self.records.retain(f);
+    }
+
+    /// Removes and returns the first record with ID `id`, if any
+    pub fn remove(&mut self, id: &str) -> Option<Record> {
+        let index = self.records.iter().position(|x| x.id() == id)?;
+        Some(self.records.remove(index))
+    }
+
+    /// Returns an iterator over the records, in alignment order
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC-T");
+    /// let ids: Vec<&str> = msa.iter().map(|x| x.id()).collect();
+    /// assert_eq!(ids, vec!["id1", "id2"]);
+    /// ```
+    pub fn iter(&self) -> std::slice::Iter<'_, Record> {
+        self.records.iter()
+    }
+
+    /// Returns an iterator over mutable references to the records
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Record> {
+        self.records.iter_mut()
+    }
+
+    /// Removes and returns the first record with ID `id`, keeping the others
+    /// in order. Same as [`MSA::remove`].
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC-T");
+    /// assert_eq!(msa.remove_record("id1").unwrap().sequence(), "ACGT");
+    /// assert!(msa.remove_record("id3").is_none());
+    /// assert_eq!(msa.len(), 1);
+    /// ```
+    pub fn remove_record(&mut self, id: &str) -> Option<Record> {
+        self.remove(id)
+    }
+
+    /// Sorts the records by ID. The sort is stable, records with the same
+    /// ID keeping their order.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id2", "AC");
+    /// msa.push_record("id1", "AG");
+    /// msa.sort_by_id();
+    /// assert_eq!(msa.get(0).unwrap().id(), "id1");
+    /// ```
+    pub fn sort_by_id(&mut self) {
+        self.sort_by(|a, b| a.id().cmp(b.id()));
+    }
+
+    /// Sorts the records with the comparator `cmp`. The sort is stable, and
+    /// annotations are left untouched.
+    pub fn sort_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&Record, &Record) -> std::cmp::Ordering,
+    {
+        self.records.sort_by(cmp);
+    }
+
+    /// Moves the records to the order of `ids`. Records whose ID is not
+    /// listed are moved after the others, in their current order, if
+    /// `append_unlisted` is `true`. Fails, leaving the alignment unchanged,
+    /// if an ID is listed twice or has no record, or if a record is not
+    /// listed and `append_unlisted` is `false`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "AG");
+    /// msa.push_record("id3", "AT");
+    /// msa.reorder(&["id3", "id1"], true).unwrap();
+    /// assert_eq!(msa.get(0).unwrap().id(), "id3");
+    /// assert_eq!(msa.get(2).unwrap().id(), "id2");
+    /// assert!(msa.reorder(&["id3", "id1"], false).is_err());
+    /// ```
+    pub fn reorder(&mut self, ids: &[&str], append_unlisted: bool) -> Result<(), Error> {
+        let mut listed = HashSet::new();
+        let mut duplicated = Vec::new();
+        for &id in ids {
+            if !listed.insert(id) && !duplicated.iter().any(|x| x == id) {
+                duplicated.push(id.to_string());
+            }
+        }
+        let missing: Vec<String> = listed
+            .iter()
+            .filter(|&&id| !self.contains(id))
+            .map(|id| id.to_string())
+            .collect();
+        let unlisted: Vec<String> = match append_unlisted {
+            true => Vec::new(),
+            false => self
+                .records
+                .iter()
+                .filter(|x| !listed.contains(x.id()))
+                .map(|x| x.id().to_string())
+                .collect(),
         };
-        if to_continue.is_empty() {
-            write!(f, "{}{}\n{}", header, column_desc, string)
-        } else {
-            write!(f, "{}{}\n{}\n{}", header, column_desc, string, to_continue)
+        if !(missing.is_empty() && duplicated.is_empty() && unlisted.is_empty()) {
+            let mut missing = missing;
+            missing.sort();
+            return Err(Error::InvalidOrder {
+                missing,
+                duplicated,
+                unlisted,
+            });
         }
+
+        let position: HashMap<&str, usize> =
+            ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        self.records
+            .sort_by_key(|x| position.get(x.id()).copied().unwrap_or(ids.len()));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::record::Record;
+    pub(crate) fn records(&self) -> &[Record] {
+        &self.records
+    }
 
-    #[test]
-    fn msa_new() {
-        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+    /// Returns an iterator over the alignment annotations, in arbitrary order
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.add_annotation("ID".to_string(), "globin".to_string());
+    /// let annotations: Vec<_> = msa.annotations().collect();
+    /// assert_eq!(annotations, vec![(&"ID".to_string(), &"globin".to_string())]);
+    /// ```
+    pub fn annotations(&self) -> impl Iterator<Item = (&String, &String)> + '_ {
+        self.annotations.iter()
+    }
 
-        let mut annotations = HashMap::new();
-        annotations.insert(String::from("author"), String::from("John Doe"));
+    /// Returns an iterator over the column annotations, in arbitrary order
+    pub fn column_annotations(&self) -> impl Iterator<Item = (&String, &String)> + '_ {
+        self.column_annotations.iter()
+    }
 
-        let mut column_annotations = HashMap::new();
-        column_annotations.insert(String::from("1"), String::from("column 1"));
+    /// Returns `true` if every record has the same length as the first one
+    pub fn is_rectangular(&self) -> bool {
+        self.first_length_mismatch().is_none()
+    }
 
-        let msa = MSA::new(
-            records.clone(),
-            annotations.clone(),
-            column_annotations.clone(),
-        );
+    /// Checks that every record, and every column annotation, has the same
+    /// length as the first record, which the column-wise methods assume.
+    /// The error reports the first record or annotation that disagrees.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC");
+    /// assert!(!msa.is_rectangular());
+    /// assert_eq!(
+    ///     msa.validate().unwrap_err().to_string(),
+    ///     "Sequence id2 has length 2 but alignment has 4 columns"
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let expected = self.col_len();
+        if let Some((id, found)) = self.first_length_mismatch() {
+            return Err(Error::UnequalLength {
+                id: id.to_string(),
+                expected,
+                found,
+            });
+        }
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut tracks: Vec<(&String, usize)> = self
+            .column_annotations
+            .iter()
+            .map(|(name, value)| (name, value.len()))
+            .chain(
+                self.column_scores
+                    .iter()
+                    .map(|(name, values)| (name, values.len())),
+            )
+            .collect();
+        tracks.sort();
+        match tracks.into_iter().find(|&(_, len)| len != expected) {
+            Some((name, found)) => Err(Error::UnequalAnnotationLength {
+                name: name.clone(),
+                expected,
+                found,
+            }),
+            None => Ok(()),
+        }
+    }
 
-        assert_eq!(msa.records, records);
-        assert_eq!(msa.annotations, annotations);
-        assert_eq!(msa.column_annotations, column_annotations);
+    /// Returns the ID and length of the first record whose length differs
+    /// from the alignment column count
+    fn first_length_mismatch(&self) -> Option<(&str, usize)> {
+        let col_len = self.col_len();
+        self.records
+            .iter()
+            .find(|x| x.len() != col_len)
+            .map(|x| (x.id(), x.len()))
     }
 
-    #[test]
-    fn msa_len() {
-        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+    /// Returns the record at position `index`, in alignment order
+    pub fn get(&self, index: usize) -> Option<&Record> {
+        self.records.get(index)
+    }
 
-        let msa = MSA::new(records.clone(), HashMap::new(), HashMap::new());
-        assert_eq!(msa.len(), 2);
+    /// Returns the record with ID `id`, scanning the records in order.
+    /// Same as [`MSA::get_record`].
+    pub fn get_by_id(&self, id: &str) -> Option<&Record> {
+        self.get_record(id)
     }
 
-    #[test]
-    fn msa_alignment_len() {
-        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+    /// Returns the record with ID `id`. With duplicate IDs, the first record
+    /// is returned, which is also the one `push_record` appends to.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// assert_eq!(msa.get_record("id1").unwrap().sequence(), "ACGT");
+    /// assert!(msa.get_record("id2").is_none());
+    /// ```
+    pub fn get_record(&self, id: &str) -> Option<&Record> {
+        self.records.iter().find(|x| x.id() == id)
+    }
 
-        let msa = MSA::new(records.clone(), HashMap::new(), HashMap::new());
-        assert_eq!(msa.col_len(), 4);
+    /// Returns a mutable reference to the first record with ID `id`
+    pub fn get_record_mut(&mut self, id: &str) -> Option<&mut Record> {
+        self.records.iter_mut().find(|x| x.id() == id)
     }
 
-    #[test]
-    fn msa_push_valid_sequence() {
-        let mut msa = MSA::default();
-        msa.push_record("id1", "ACGT");
-        assert_eq!(msa.len(), 1);
-        assert_eq!(msa.records[0], Record::new("id1", "ACGT"));
+    /// Append `value` to the `name` annotation of the first record with ID `id`.
+    /// Does nothing if no such record exists.
+    pub fn push_record_annotation(&mut self, id: &str, name: &str, value: &str) {
+        if let Some(record) = self.get_record_mut(id) {
+            record.push_annotation(name, value);
+        }
     }
 
-    #[test]
-    fn msa_print_no_seqs() {
-        let msa = MSA::default();
-        assert_eq!(msa.to_string(), "No sequence in alignment");
+    /// Returns the `name` annotation of the first record with ID `id`
+    pub fn get_record_annotation(&self, id: &str, name: &str) -> Option<&String> {
+        self.get_record(id).and_then(|x| x.get_annotation(name))
     }
-    #[test]
-    fn msa_print_one_seqs() {
-        let mut msa = MSA::default();
-        msa.push_record("id1", "ACG");
-        assert_eq!(
-            msa.to_string(),
-            "Alignment with 1 row and 3 columns\nid1\tACG\n"
-        );
+
+    /// Returns the residues of every record at column `index`, in record
+    /// order, or `None` if `index` is out of range. Each byte is taken as one
+    /// residue, so non-ASCII content cannot cause a panic. Like
+    /// [`MSA::column`], records too short to reach the column contribute a
+    /// `-`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC");
+    /// assert_eq!(msa.get_column(1), Some("CC".to_string()));
+    /// assert_eq!(msa.get_column(3), Some("T-".to_string()));
+    /// assert_eq!(msa.get_column(4), None);
+    /// ```
+    pub fn get_column(&self, index: usize) -> Option<String> {
+        self.column(index)
+            .map(|c| c.into_iter().map(char::from).collect())
+    }
+
+    /// Returns the bytes of every record at column `i`, in record order, or
+    /// `None` if `i` is not below `col_len()`. Records too short to reach the
+    /// column contribute a `-`, so the result always has `len()` bytes.
+    pub fn column(&self, i: usize) -> Option<Vec<u8>> {
+        if i >= self.col_len() {
+            return None;
+        }
+        Some(
+            self.records
+                .iter()
+                .map(|x| x.sequence().as_bytes().get(i).copied().unwrap_or(b'-'))
+                .collect(),
+        )
+    }
+
+    /// Returns an iterator over the `col_len()` columns, as given by
+    /// [`MSA::column`]. Columns past the end of the first record are not
+    /// visited; shorter records are padded with `-`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "A-");
+    /// let columns: Vec<Vec<u8>> = msa.columns().collect();
+    /// assert_eq!(columns, vec![b"AA".to_vec(), b"C-".to_vec()]);
+    /// ```
+    pub fn columns(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.col_len()).filter_map(move |i| self.column(i))
+    }
+
+    /// Returns every column as given by [`MSA::columns`], transposing the
+    /// records in a single pass over their sequences. Prefer it over
+    /// [`MSA::column`] calls when visiting all columns of large alignments.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "A");
+    /// assert_eq!(msa.to_columns(), vec![b"AA".to_vec(), b"C-".to_vec()]);
+    /// ```
+    pub fn to_columns(&self) -> Vec<Vec<u8>> {
+        self.transpose(Some(b'-'))
+    }
+
+    /// Transpose the records into their `col_len()` first columns. Records
+    /// too short to reach a column are padded with `pad`, or left out of it.
+    fn transpose(&self, pad: Option<u8>) -> Vec<Vec<u8>> {
+        let mut columns = vec![Vec::with_capacity(self.len()); self.col_len()];
+        for record in &self.records {
+            let bytes = record.sequence().as_bytes();
+            for (i, column) in columns.iter_mut().enumerate() {
+                match bytes.get(i).copied().or(pad) {
+                    Some(b) => column.push(b),
+                    None => break,
+                }
+            }
+        }
+        columns
+    }
+
+    /// Number of occurrences of every byte value in each column
+    fn column_byte_counts(&self) -> Vec<[usize; 256]> {
+        self.transpose(None)
+            .iter()
+            .map(|column| {
+                let mut counts = [0usize; 256];
+                for &b in column {
+                    counts[b as usize] += 1;
+                }
+                counts
+            })
+            .collect()
+    }
+
+    /// Build a new alignment from the given columns, in the given order.
+    /// Column annotations are subset alongside the sequences.
+    fn select_columns(&self, columns: &[usize]) -> MSA {
+        let pick = |s: &str| -> String {
+            let bytes = s.as_bytes();
+            columns
+                .iter()
+                .filter_map(|&col| bytes.get(col).map(|&b| b as char))
+                .collect()
+        };
+        MSA {
+            records: self
+                .records
+                .iter()
+                .map(|x| x.with_sequence(pick(x.sequence())))
+                .collect(),
+            annotations: self.annotations.clone(),
+            column_annotations: self
+                .column_annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), pick(v)))
+                .collect(),
+            column_scores: self
+                .column_scores
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        columns
+                            .iter()
+                            .filter_map(|&col| v.get(col).copied())
+                            .collect(),
+                    )
+                })
+                .collect(),
+            gaps: self.gaps.clone(),
+        }
+    }
+
+    /// Returns a new alignment restricted to the columns in `range`, for every
+    /// record. Column annotations are sliced to the same range and alignment
+    /// annotations are copied over.
+    ///
+    /// # Panics
+    /// Panics if `range` is decreasing or ends past `col_len()`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGTAC");
+    /// assert_eq!(msa.slice(1..4).to_string(), "Alignment with 1 row and 3 columns\nid1\tCGT\n");
+    /// ```
+    pub fn slice(&self, range: Range<usize>) -> MSA {
+        assert!(
+            range.start <= range.end && range.end <= self.col_len(),
+            "column range {}..{} out of bounds for alignment with {} columns",
+            range.start,
+            range.end,
+            self.col_len()
+        );
+        self.select_columns(&range.collect::<Vec<_>>())
+    }
+
+    /// Returns a new alignment restricted to the columns in `range`, like
+    /// [`MSA::slice`], but with both ends clamped to `col_len()` instead of
+    /// panicking. A decreasing range gives an alignment without columns.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGTAC");
+    /// assert_eq!(msa.slice_columns(4..10).col_len(), 2);
+    /// ```
+    pub fn slice_columns(&self, range: Range<usize>) -> MSA {
+        let end = range.end.min(self.col_len());
+        let start = range.start.min(end);
+        self.select_columns(&(start..end).collect::<Vec<_>>())
+    }
+
+    /// Splits the columns in two alignments: the columns where `keep` is
+    /// `true`, and the others, each with its share of the column annotations.
+    /// Columns past the end of `keep` go to the second alignment.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC-T");
+    /// let (kept, removed) = msa.partition_by_reliability(&[true, true, false, true]);
+    /// assert_eq!(kept.col_len() + removed.col_len(), msa.col_len());
+    /// assert_eq!(removed.to_string(), "Alignment with 1 row and 1 column\nid1\t-\n");
+    /// ```
+    pub fn partition_by_reliability(&self, keep: &[bool]) -> (MSA, MSA) {
+        let (kept, removed): (Vec<usize>, Vec<usize>) =
+            (0..self.col_len()).partition(|&col| keep.get(col) == Some(&true));
+        (self.select_columns(&kept), self.select_columns(&removed))
+    }
+
+    /// Converts the sequence of every record to uppercase in place, see
+    /// [`Record::to_uppercase`]
+    pub fn to_uppercase(&mut self) {
+        self.records.iter_mut().for_each(Record::to_uppercase);
+    }
+
+    /// Converts the sequence of every record to lowercase in place, see
+    /// [`Record::to_lowercase`]
+    pub fn to_lowercase(&mut self) {
+        self.records.iter_mut().for_each(Record::to_lowercase);
+    }
+
+    /// Rewrites every `-`, `.` and `~` of the records as `gap_char`, see
+    /// [`MSA::normalize_gaps_with`]
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "A.C~G-");
+    /// msa.normalize_gaps('-');
+    /// assert_eq!(msa.get(0).unwrap().sequence(), "A-C-G-");
+    /// ```
+    pub fn normalize_gaps(&mut self, gap_char: char) {
+        self.normalize_gaps_with(&GapSet::from(NORMALIZED_GAP_CHARS), gap_char);
+    }
+
+    /// Rewrites every character of `gaps` in the records as `gap_char`.
+    /// IDs, record annotations and column annotations, such as `SS_cons`,
+    /// are left untouched.
+    pub fn normalize_gaps_with(&mut self, gaps: &GapSet, gap_char: char) {
+        for record in self.records.iter_mut() {
+            record.normalize_gaps(gaps, gap_char);
+        }
+    }
+
+    /// Returns the alignment with every record reverse complemented (see
+    /// [`Record::reverse_complement`]) and the column annotations reversed.
+    /// Fails on the first record that is not made of nucleotides.
+    pub fn reverse_complement(&self) -> Result<MSA, Error> {
+        Ok(MSA {
+            records: self
+                .records
+                .iter()
+                .map(|x| x.reverse_complement())
+                .collect::<Result<_, _>>()?,
+            annotations: self.annotations.clone(),
+            column_annotations: self
+                .column_annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.chars().rev().collect()))
+                .collect(),
+            column_scores: self
+                .column_scores
+                .iter()
+                .map(|(k, v)| (k.clone(), v.iter().rev().copied().collect()))
+                .collect(),
+            gaps: self.gaps.clone(),
+        })
+    }
+
+    /// Returns an alignment made of the distinct columns only, keeping the
+    /// first occurrence of each, along with a vector mapping every original
+    /// column to the index of its representative in the returned alignment.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AAC");
+    /// msa.push_record("id2", "GGT");
+    /// let (unique, mapping) = msa.unique_columns();
+    /// assert_eq!(unique.col_len(), 2);
+    /// assert_eq!(mapping, vec![0, 0, 1]);
+    /// ```
+    pub fn unique_columns(&self) -> (MSA, Vec<usize>) {
+        let op = Operation::start("unique_columns");
+        let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut kept = Vec::new();
+        let mut mapping = Vec::with_capacity(self.col_len());
+
+        for (col, column) in self.to_columns().into_iter().enumerate() {
+            let index = *seen.entry(column).or_insert_with(|| {
+                kept.push(col);
+                kept.len() - 1
+            });
+            mapping.push(index);
+        }
+
+        let unique = self.select_columns(&kept);
+        trace::debug!(
+            collapsed_columns = self.col_len() - unique.col_len(),
+            "collapsed duplicate columns"
+        );
+        op.finish(&unique);
+        (unique, mapping)
+    }
+
+    /// Returns, for each column, the number of occurrences of every character,
+    /// case sensitive. Gaps are counted under their own character.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "A-");
+    /// let profile = msa.profile();
+    /// assert_eq!(profile[0][&'A'], 2);
+    /// assert_eq!(profile[1][&'-'], 1);
+    /// ```
+    pub fn profile(&self) -> Vec<HashMap<char, usize>> {
+        self.column_byte_counts()
+            .iter()
+            .map(|counts| {
+                (0..=u8::MAX)
+                    .zip(counts)
+                    .filter(|(_, &n)| n > 0)
+                    .map(|(b, &n)| (b as char, n))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Residue counts for every column, computed from a single transpose.
+    /// Gap characters are skipped when `ignore_gaps` is `true`, and otherwise
+    /// all counted as one `-` symbol.
+    fn column_counts(&self, ignore_gaps: bool) -> Vec<HashMap<u8, usize>> {
+        self.column_byte_counts()
+            .iter()
+            .map(|counts| {
+                let mut column = HashMap::new();
+                for (residue, &n) in (0..=u8::MAX).zip(counts).filter(|(_, &n)| n > 0) {
+                    let residue = match self.gaps.is_gap(&residue) {
+                        true if ignore_gaps => continue,
+                        true => b'-',
+                        false => residue,
+                    };
+                    *column.entry(residue).or_insert(0) += n;
+                }
+                column
+            })
+            .collect()
+    }
+
+    /// Returns, for each column, the fraction of records with a gap (see
+    /// [`MSA::set_gaps`]) at that position. An empty alignment gives an
+    /// empty vector.
+    pub fn gap_fractions(&self) -> Vec<f64> {
+        self.gap_fractions_with(&self.gaps)
+    }
+
+    /// Returns the gap fraction of every column like
+    /// [`MSA::gap_fractions`], counting the characters of `gaps` as gaps
+    /// # Example
+    /// ```
+    /// use align_rs::msa::{GapSet, MSA};
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "~AC.");
+    /// msa.push_record("id2", "-AC-");
+    /// assert_eq!(msa.gap_fractions_with(&GapSet::new(&['-', '~'])), vec![1.0, 0.0, 0.0, 0.5]);
+    /// ```
+    pub fn gap_fractions_with(&self, gaps: &GapSet) -> Vec<f64> {
+        self.column_byte_counts()
+            .iter()
+            .map(|counts| {
+                (0..=u8::MAX)
+                    .zip(counts)
+                    .filter(|(b, _)| gaps.is_gap(b))
+                    .map(|(_, &n)| n)
+                    .sum::<usize>()
+            })
+            .map(|n| n as f64 / self.len() as f64)
+            .collect()
+    }
+
+    /// Returns the gap fraction of every column, same as
+    /// [`MSA::gap_fractions`]
+    pub fn gap_profile(&self) -> Vec<f64> {
+        self.gap_fractions()
+    }
+
+    /// Returns the columns whose gap fraction is at least `threshold`, where
+    /// indels of a new sequence would preferably be placed
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC-T");
+    /// msa.push_record("id2", "A--T");
+    /// assert_eq!(msa.preferred_gap_columns(0.5), vec![1, 2]);
+    /// ```
+    pub fn preferred_gap_columns(&self, threshold: f64) -> Vec<usize> {
+        self.gap_fractions()
+            .iter()
+            .enumerate()
+            .filter(|(_, &fraction)| fraction >= threshold)
+            .map(|(col, _)| col)
+            .collect()
+    }
+
+    /// Returns, for each column, the sum of the weights of the records that
+    /// do not have a gap of `gaps` there. `weights` pairs record IDs with
+    /// their weight; with duplicate IDs the first pair is used.
+    ///
+    /// # Panics
+    /// Panics if a record has no weight in `weights`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "A-");
+    /// let weights = vec![("id1".to_string(), 0.25), ("id2".to_string(), 0.75)];
+    /// assert_eq!(msa.column_effective_n(&weights, msa.gaps()), vec![1.0, 0.25]);
+    /// ```
+    pub fn column_effective_n(&self, weights: &[(String, f64)], gaps: &GapSet) -> Vec<f64> {
+        let mut effective = vec![0.0; self.col_len()];
+        for record in &self.records {
+            let weight = weights
+                .iter()
+                .find(|(id, _)| id == record.id())
+                .map(|&(_, w)| w)
+                .unwrap_or_else(|| panic!("no weight for record {}", record.id()));
+            for (n, residue) in effective.iter_mut().zip(record.sequence().chars()) {
+                if !gaps.contains(residue) {
+                    *n += weight;
+                }
+            }
+        }
+        effective
+    }
+
+    /// Returns the number of records with a gap at column `col`, or `None` if
+    /// `col` is out of range
+    pub fn gap_count(&self, col: usize) -> Option<usize> {
+        if col >= self.col_len() {
+            return None;
+        }
+        Some(
+            self.records
+                .iter()
+                .filter(
+                    |x| matches!(x.sequence().as_bytes().get(col), Some(b) if self.gaps.is_gap(b)),
+                )
+                .count(),
+        )
+    }
+
+    /// Removes the columns made only of gaps, from every record and column
+    /// annotation, so that all of them stay aligned
+    pub fn remove_gap_columns(&mut self) {
+        let gaps = self.gaps.clone();
+        self.remove_gap_columns_with(&gaps);
+    }
+
+    /// Removes the columns made only of the characters of `gaps`, like
+    /// [`MSA::remove_gap_columns`]
+    pub fn remove_gap_columns_with(&mut self, gaps: &GapSet) {
+        self.remove_columns_where(gaps, |fraction| fraction >= 1.0);
+    }
+
+    /// Removes the columns whose gap fraction is strictly above `f`, from
+    /// every record and column annotation, and returns the original indices
+    /// of the retained columns. `0.0` keeps only the columns without gaps.
+    pub fn remove_columns_above_gap_fraction(&mut self, f: f64) -> Vec<usize> {
+        let gaps = self.gaps.clone();
+        self.remove_columns_where(&gaps, |fraction| fraction > f)
+    }
+
+    /// Returns a copy of the alignment without the columns whose gap fraction
+    /// is strictly above `max_gap_fraction`, along with the original indices
+    /// of the retained columns. Column annotations are trimmed alike.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "A-CG");
+    /// msa.push_record("id2", "A--G");
+    /// let (trimmed, kept) = msa.trim_gap_columns(0.0);
+    /// assert_eq!(trimmed.col_len(), 2);
+    /// assert_eq!(kept, vec![0, 3]);
+    /// ```
+    pub fn trim_gap_columns(&self, max_gap_fraction: f64) -> (MSA, Vec<usize>) {
+        let mut trimmed = self.clone();
+        let kept = trimmed.remove_columns_above_gap_fraction(max_gap_fraction);
+        (trimmed, kept)
+    }
+
+    /// Returns a new alignment without the columns where record `ref_id` has
+    /// a gap, so that columns map one-to-one onto the ungapped reference
+    /// positions. Column annotations and scores are trimmed alike. Returns
+    /// `None` if there is no such record.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("ref", "A-CG.T");
+    /// msa.push_record("other", "AGC--T");
+    /// let trimmed = msa.trim_to_reference("ref").unwrap();
+    /// assert_eq!(trimmed.get_record("ref").unwrap().sequence(), "ACGT");
+    /// assert_eq!(trimmed.get_record("other").unwrap().sequence(), "AC-T");
+    /// ```
+    pub fn trim_to_reference(&self, ref_id: &str) -> Option<MSA> {
+        let reference = self.get_record(ref_id)?;
+        let op = Operation::start("trim_to_reference");
+        let kept: Vec<usize> = reference
+            .sequence()
+            .bytes()
+            .enumerate()
+            .filter(|(_, b)| !self.gaps.is_gap(b))
+            .map(|(col, _)| col)
+            .collect();
+        trace::debug!(
+            removed_columns = self.col_len() - kept.len(),
+            "removed reference gap columns"
+        );
+        let trimmed = self.select_columns(&kept);
+        op.finish(&trimmed);
+        Some(trimmed)
+    }
+
+    /// Drop every column whose gap fraction satisfies `remove` and return the
+    /// indices of the others
+    fn remove_columns_where<F>(&mut self, gaps: &GapSet, remove: F) -> Vec<usize>
+    where
+        F: Fn(f64) -> bool,
+    {
+        let op = Operation::start("remove_gap_columns");
+        let kept: Vec<usize> = self
+            .gap_fractions_with(gaps)
+            .iter()
+            .enumerate()
+            .filter(|(_, &fraction)| !remove(fraction))
+            .map(|(col, _)| col)
+            .collect();
+        trace::debug!(
+            removed_columns = self.col_len() - kept.len(),
+            "removed gapped columns"
+        );
+        *self = self.select_columns(&kept);
+        op.finish(self);
+        kept
+    }
+
+    /// Re-threads the alignment so that the gaps of record `shared_id` match
+    /// its gaps in `template`. Gap columns are inserted in every record where
+    /// the template has more gaps between two residues; columns are dropped
+    /// where it has fewer, which is only allowed for columns that are gaps in
+    /// every record. New column annotation positions are filled with spaces.
+    ///
+    /// On error the alignment is left untouched.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut target = MSA::default();
+    /// target.push_record("ref", "ACGT");
+    /// target.push_record("other", "AGGT");
+    /// let mut template = MSA::default();
+    /// template.push_record("ref", "A-CG-T");
+    /// target.rethread(&template, "ref").unwrap();
+    /// assert_eq!(
+    ///     target.to_string(),
+    ///     "Alignment with 2 rows and 6 columns\nref\tA-CG-T\nother\tA-GG-T\n"
+    /// );
+    /// ```
+    pub fn rethread(&mut self, template: &MSA, shared_id: &str) -> Result<(), Error> {
+        let op = Operation::start("rethread");
+        let find = |msa: &MSA| -> Result<Vec<u8>, Error> {
+            msa.records
+                .iter()
+                .find(|x| x.id() == shared_id)
+                .map(|x| x.sequence().as_bytes().to_vec())
+                .ok_or_else(|| Error::RecordNotFound {
+                    id: shared_id.to_string(),
+                })
+        };
+        let (current, wanted) = (find(self)?, find(template)?);
+        let residues = |seq: &[u8]| -> Vec<u8> {
+            seq.iter()
+                .filter(|x| !self.gaps.is_gap(x))
+                .map(|x| x.to_ascii_uppercase())
+                .collect()
+        };
+        if residues(&current) != residues(&wanted) {
+            return Err(Error::IncompatibleRecord {
+                id: shared_id.to_string(),
+                message: "residues differ from the template".to_string(),
+            });
+        }
+
+        // Gap columns of the shared record before each residue, plus the
+        // trailing ones, in the alignment and in the template
+        let segments = |seq: &[u8]| -> Vec<Vec<usize>> {
+            let mut segments = vec![Vec::new()];
+            for (col, b) in seq.iter().enumerate() {
+                if self.gaps.is_gap(b) {
+                    segments.last_mut().unwrap().push(col);
+                } else {
+                    segments.push(Vec::new());
+                }
+            }
+            segments
+        };
+        let all_gaps = |col: usize| {
+            self.records.iter().all(
+                |x| !matches!(x.sequence().as_bytes().get(col), Some(b) if !self.gaps.is_gap(b)),
+            )
+        };
+        let residue_columns: Vec<usize> = (0..current.len())
+            .filter(|&col| !self.gaps.is_gap(&current[col]))
+            .collect();
+        let mut columns: Vec<Option<usize>> = Vec::with_capacity(wanted.len());
+        for (k, (mut cols, target)) in segments(&current)
+            .into_iter()
+            .zip(segments(&wanted).iter().map(|x| x.len()))
+            .enumerate()
+        {
+            while cols.len() > target {
+                match cols.iter().rposition(|&col| all_gaps(col)) {
+                    Some(index) => {
+                        cols.remove(index);
+                    }
+                    None => {
+                        return Err(Error::IncompatibleRecord {
+                            id: shared_id.to_string(),
+                            message: format!(
+                                "columns before residue {} hold residues of other records",
+                                k + 1
+                            ),
+                        })
+                    }
+                }
+            }
+            let padding = target - cols.len();
+            columns.extend(cols.into_iter().map(Some));
+            columns.resize(columns.len() + padding, None);
+            // The residue column closing this segment, if any
+            if let Some(&col) = residue_columns.get(k) {
+                columns.push(Some(col));
+            }
+        }
+
+        let build = |s: &str, filler: char| -> String {
+            let bytes = s.as_bytes();
+            columns
+                .iter()
+                .map(|col| match col {
+                    Some(col) => bytes.get(*col).map(|&b| b as char).unwrap_or(filler),
+                    None => filler,
+                })
+                .collect()
+        };
+        self.records = self
+            .records
+            .iter()
+            .map(|x| x.with_sequence(build(x.sequence(), '-')))
+            .collect();
+        self.column_annotations = self
+            .column_annotations
+            .iter()
+            .map(|(k, v)| (k.clone(), build(v, ' ')))
+            .collect();
+        self.column_scores = self
+            .column_scores
+            .iter()
+            .map(|(k, v)| {
+                let values = columns
+                    .iter()
+                    .map(|col| col.and_then(|col| v.get(col).copied()).unwrap_or(f64::NAN))
+                    .collect();
+                (k.clone(), values)
+            })
+            .collect();
+        op.finish(self);
+        Ok(())
+    }
+
+    /// Returns, for each column, the number of record pairs whose residues
+    /// differ at that column. With `ignore_gaps`, pairs involving a gap are
+    /// not compared; otherwise the gap is treated as one more residue.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// msa.push_record("id2", "AG");
+    /// msa.push_record("id3", "A-");
+    /// assert_eq!(msa.column_mismatch_counts(true), vec![0, 1]);
+    /// assert_eq!(msa.column_mismatch_counts(false), vec![0, 3]);
+    /// ```
+    pub fn column_mismatch_counts(&self, ignore_gaps: bool) -> Vec<usize> {
+        self.column_counts(ignore_gaps)
+            .iter()
+            .map(|counts| {
+                // Differing pairs are all pairs minus the pairs sharing a residue
+                let total: usize = counts.values().sum();
+                let same: usize = counts.values().map(|&n| n * n).sum();
+                (total * total - same) / 2
+            })
+            .collect()
+    }
+
+    /// Returns the nucleotide diversity (pi): the average number of pairwise
+    /// differences per site, i.e. the column mismatch counts summed over the
+    /// alignment, divided by the number of record pairs and by `col_len()`.
+    /// Returns `0.0` when there are fewer than two records.
+    pub fn nucleotide_diversity(&self, ignore_gaps: bool) -> f64 {
+        let n = self.len();
+        if n < 2 || self.col_len() == 0 {
+            return 0.0;
+        }
+        let pairs = (n * (n - 1) / 2) as f64;
+        let differences: usize = self.column_mismatch_counts(ignore_gaps).iter().sum();
+        differences as f64 / pairs / self.col_len() as f64
+    }
+
+    /// Returns the number of segregating (polymorphic) columns, i.e. columns
+    /// holding more than one distinct residue. With `ignore_gaps`, gaps are
+    /// not counted as a residue.
+    pub fn segregating_sites(&self, ignore_gaps: bool) -> usize {
+        self.column_counts(ignore_gaps)
+            .iter()
+            .filter(|counts| counts.len() > 1)
+            .count()
+    }
+
+    /// Returns Watterson's estimator theta_W = S / a_n, where S is the number
+    /// of segregating sites and a_n the harmonic number of n - 1 for n records.
+    /// Returns `0.0` when there are fewer than two records.
+    pub fn wattersons_theta(&self, ignore_gaps: bool) -> f64 {
+        if self.len() < 2 {
+            return 0.0;
+        }
+        self.segregating_sites(ignore_gaps) as f64 / harmonic(self.len() - 1)
+    }
+
+    /// Returns Tajima's D (Tajima 1989), the normalized difference between the
+    /// mean number of pairwise differences and Watterson's theta. Returns
+    /// `None` when it is undefined: fewer than four records or no segregating
+    /// sites.
+    pub fn tajimas_d(&self, ignore_gaps: bool) -> Option<f64> {
+        let n = self.len();
+        let segregating = self.segregating_sites(ignore_gaps) as f64;
+        if n < 4 || segregating == 0.0 {
+            return None;
+        }
+
+        let k = self.nucleotide_diversity(ignore_gaps) * self.col_len() as f64;
+        let n = n as f64;
+        let a1 = harmonic(self.len() - 1);
+        let a2: f64 = (1..self.len()).map(|i| 1.0 / (i * i) as f64).sum();
+        let b1 = (n + 1.0) / (3.0 * (n - 1.0));
+        let b2 = 2.0 * (n * n + n + 3.0) / (9.0 * n * (n - 1.0));
+        let c1 = b1 - 1.0 / a1;
+        let c2 = b2 - (n + 2.0) / (a1 * n) + a2 / (a1 * a1);
+        let e1 = c1 / a1;
+        let e2 = c2 / (a1 * a1 + a2);
+
+        let variance = e1 * segregating + e2 * segregating * (segregating - 1.0);
+        Some((k - segregating / a1) / variance.sqrt())
+    }
+
+    /// Returns a TCS-like reliability score for each column: the fraction of
+    /// residue pairs aligned in that column that are also aligned by the
+    /// optimal global pairwise alignment (scored with `matrix`) of the two
+    /// ungapped sequences. Columns without any residue pair score `0.0`.
+    pub fn column_tcs(&self, matrix: &ScoringMatrix) -> Vec<f64> {
+        let col_len = self.col_len();
+
+        // Residue index of each column in every record, `None` for gaps
+        let positions: Vec<Vec<Option<usize>>> = self
+            .records
+            .iter()
+            .map(|x| {
+                let mut index = 0;
+                x.sequence()
+                    .bytes()
+                    .map(|b| {
+                        if self.gaps.is_gap(&b) {
+                            return None;
+                        }
+                        index += 1;
+                        Some(index - 1)
+                    })
+                    .collect()
+            })
+            .collect();
+        let ungapped: Vec<Vec<u8>> = self
+            .records
+            .iter()
+            .map(|x| {
+                x.sequence()
+                    .bytes()
+                    .filter(|b| !self.gaps.is_gap(b))
+                    .collect()
+            })
+            .collect();
+
+        let mut supported = vec![0usize; col_len];
+        let mut total = vec![0usize; col_len];
+        for i in 0..self.len() {
+            for j in i + 1..self.len() {
+                let library: HashSet<(usize, usize)> =
+                    global_align(&ungapped[i], &ungapped[j], matrix)
+                        .aligned_pairs()
+                        .collect();
+                for col in 0..col_len {
+                    let pair = (positions[i].get(col), positions[j].get(col));
+                    if let (Some(&Some(p)), Some(&Some(q))) = pair {
+                        total[col] += 1;
+                        if library.contains(&(p, q)) {
+                            supported[col] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        supported
+            .iter()
+            .zip(&total)
+            .map(|(&s, &t)| if t == 0 { 0.0 } else { s as f64 / t as f64 })
+            .collect()
+    }
+
+    /// Returns the matrix of pairwise identities, where entry `[i][j]` is the
+    /// fraction of identical residues between records `i` and `j` over the
+    /// columns where neither has a gap. The matrix is symmetric with `1.0` on
+    /// the diagonal; pairs without any such column get `0.0`.
+    /// See [`MSA::identity_matrix_with`] to count gaps as mismatches.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC-A");
+    /// assert_eq!(msa.identity_matrix(), vec![vec![1.0, 2.0 / 3.0], vec![2.0 / 3.0, 1.0]]);
+    /// ```
+    pub fn identity_matrix(&self) -> Vec<Vec<f64>> {
+        self.identity_matrix_with(GapPolicy::default())
+    }
+
+    /// Returns the matrix of pairwise identities, handling gaps according to
+    /// `gaps`. Columns where both records have a gap are always skipped.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::{GapPolicy, MSA};
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT-");
+    /// msa.push_record("id2", "AC-A-");
+    /// assert_eq!(msa.identity_matrix_with(GapPolicy::Mismatch)[0][1], 0.5);
+    /// ```
+    pub fn identity_matrix_with(&self, gaps: GapPolicy) -> Vec<Vec<f64>> {
+        let n = self.len();
+        let mut matrix = vec![vec![1.0; n]; n];
+        for (i, a) in self.records.iter().enumerate() {
+            for (j, b) in self.records.iter().enumerate().skip(i + 1) {
+                let identity = pair_identity(
+                    a.sequence().as_bytes(),
+                    b.sequence().as_bytes(),
+                    &self.gaps,
+                    gaps,
+                );
+                matrix[i][j] = identity;
+                matrix[j][i] = identity;
+            }
+        }
+        matrix
+    }
+
+    /// Returns the identity between the records with IDs `id1` and `id2`, as
+    /// computed by [`MSA::identity_matrix`], or `None` if either is missing
+    pub fn pairwise_identity(&self, id1: &str, id2: &str) -> Option<f64> {
+        let find = |id: &str| self.records.iter().find(|x| x.id() == id);
+        let (a, b) = (find(id1)?, find(id2)?);
+        Some(pair_identity(
+            a.sequence().as_bytes(),
+            b.sequence().as_bytes(),
+            &self.gaps,
+            GapPolicy::default(),
+        ))
+    }
+
+    /// Removes every record whose identity to an earlier retained record is
+    /// at least `threshold`, as computed by [`MSA::identity_matrix`], and
+    /// returns the IDs of the removed records in alignment order.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "ACGA");
+    /// msa.push_record("id3", "ACGT");
+    /// assert_eq!(msa.dedupe(1.0), vec!["id3".to_string()]);
+    /// assert_eq!(msa.len(), 2);
+    /// ```
+    pub fn dedupe(&mut self, threshold: f64) -> Vec<String> {
+        let op = Operation::start("dedupe");
+        let mut kept: Vec<Record> = Vec::with_capacity(self.len());
+        let mut removed = Vec::new();
+        for record in self.records.drain(..) {
+            let duplicate = kept.iter().any(|x| {
+                pair_identity(
+                    x.sequence().as_bytes(),
+                    record.sequence().as_bytes(),
+                    &self.gaps,
+                    GapPolicy::default(),
+                ) >= threshold
+            });
+            if duplicate {
+                removed.push(record.id().to_string());
+            } else {
+                kept.push(record);
+            }
+        }
+        self.records = kept;
+        trace::debug!(removed = removed.len(), "collapsed duplicate records");
+        op.finish(self);
+        removed
+    }
+
+    /// Globally aligns `query` to the ungapped sequence of every record with
+    /// `scoring` and returns each record ID with the fraction of identical
+    /// residues (case insensitive). With `ignore_gaps` the fraction is taken
+    /// over the aligned residue pairs, otherwise over the whole pairwise
+    /// alignment including gaps. Gaps in `query` are removed first.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    /// use align_rs::scoring::ScoringMatrix;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC-GT");
+    /// let scoring = ScoringMatrix::new(1, -1, -1);
+    /// assert_eq!(msa.identity_to_query("ACGT", &scoring, true), vec![("id1".to_string(), 1.0)]);
+    /// assert_eq!(msa.identity_to_query("ACG", &scoring, false)[0].1, 0.75);
+    /// ```
+    pub fn identity_to_query(
+        &self,
+        query: &str,
+        scoring: &ScoringMatrix,
+        ignore_gaps: bool,
+    ) -> Vec<(String, f64)> {
+        let query: Vec<u8> = query.bytes().filter(|b| !self.gaps.is_gap(b)).collect();
+        self.records
+            .iter()
+            .map(|x| {
+                let target = x.ungapped_with(&self.gaps).into_bytes();
+                let alignment = global_align(&query, &target, scoring);
+                let (mut aligned, mut identical) = (0usize, 0usize);
+                for (i, j) in alignment.aligned_pairs() {
+                    aligned += 1;
+                    if query[i].eq_ignore_ascii_case(&target[j]) {
+                        identical += 1;
+                    }
+                }
+                let total = if ignore_gaps {
+                    aligned
+                } else {
+                    alignment.path().len()
+                };
+                let identity = if total == 0 {
+                    0.0
+                } else {
+                    identical as f64 / total as f64
+                };
+                (x.id().to_string(), identity)
+            })
+            .collect()
+    }
+
+    /// Returns a distance between the records `id_a` and `id_b` that grows
+    /// as their overlap shrinks: `1 - (1 - p) * f`, where `p` is the
+    /// fraction of mismatches over the columns where neither has a gap and
+    /// `f` is the number of such columns divided by the residue count of the
+    /// shorter record. A full overlap gives the plain p-distance. Returns
+    /// `None` if either record is missing or they do not overlap.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "ACGA");
+    /// msa.push_record("id3", "--GT");
+    /// assert_eq!(msa.coverage_adjusted_distance("id1", "id2"), Some(0.25));
+    /// assert_eq!(msa.coverage_adjusted_distance("id1", "id3"), Some(0.0));
+    /// ```
+    pub fn coverage_adjusted_distance(&self, id_a: &str, id_b: &str) -> Option<f64> {
+        let a = self.get_record(id_a)?.sequence().as_bytes();
+        let b = self.get_record(id_b)?.sequence().as_bytes();
+        let residues = |s: &[u8]| s.iter().filter(|x| !self.gaps.is_gap(x)).count();
+        let (mut overlap, mut mismatches) = (0usize, 0usize);
+        for (x, y) in a.iter().zip(b) {
+            if !self.gaps.is_gap(x) && !self.gaps.is_gap(y) {
+                overlap += 1;
+                if x != y {
+                    mismatches += 1;
+                }
+            }
+        }
+        if overlap == 0 {
+            return None;
+        }
+        let p = mismatches as f64 / overlap as f64;
+        let f = overlap as f64 / residues(a).min(residues(b)) as f64;
+        Some(1.0 - (1.0 - p) * f)
+    }
+
+    /// Returns the edges `(id, id, distance)` of a minimum spanning tree over
+    /// the records, built with Prim's algorithm from the first record. The
+    /// distance is the Hamming distance between aligned sequences; with
+    /// `ignore_gaps`, columns where either record has a gap are skipped,
+    /// otherwise a gap is one more residue. Edges are listed in the order
+    /// they join the tree, ties going to the earliest records.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AAAA");
+    /// msa.push_record("id2", "AATT");
+    /// msa.push_record("id3", "AAAT");
+    /// let edges = msa.minimum_spanning_tree(true);
+    /// assert_eq!(edges[0], ("id1".to_string(), "id3".to_string(), 1.0));
+    /// assert_eq!(edges[1], ("id3".to_string(), "id2".to_string(), 1.0));
+    /// ```
+    pub fn minimum_spanning_tree(&self, ignore_gaps: bool) -> Vec<(String, String, f64)> {
+        let op = Operation::start("minimum_spanning_tree");
+        let n = self.len();
+        let sequences: Vec<&[u8]> = self
+            .records
+            .iter()
+            .map(|x| x.sequence().as_bytes())
+            .collect();
+        let distance = |i: usize, j: usize| -> f64 {
+            sequences[i]
+                .iter()
+                .zip(sequences[j])
+                .filter(|(a, b)| {
+                    let (a_gap, b_gap) = (self.gaps.is_gap(a), self.gaps.is_gap(b));
+                    match (a_gap, b_gap) {
+                        (false, false) => a != b,
+                        _ if ignore_gaps => false,
+                        _ => a_gap != b_gap,
+                    }
+                })
+                .count() as f64
+        };
+
+        // Closest tree node and distance to it, for every node outside the tree
+        let mut closest: Vec<Option<(usize, f64)>> =
+            (0..n).map(|j| Some((0, distance(0, j)))).collect();
+        let mut edges = Vec::with_capacity(n.saturating_sub(1));
+        if n > 0 {
+            closest[0] = None;
+        }
+        while edges.len() + 1 < n {
+            let (next, parent, d) = closest
+                .iter()
+                .enumerate()
+                .filter_map(|(j, x)| x.map(|(parent, d)| (j, parent, d)))
+                .fold(None, |best: Option<(usize, usize, f64)>, x| match best {
+                    Some(b) if b.2 <= x.2 => Some(b),
+                    _ => Some(x),
+                })
+                .unwrap();
+            closest[next] = None;
+            edges.push((
+                self.records[parent].id().to_string(),
+                self.records[next].id().to_string(),
+                d,
+            ));
+            for (j, x) in closest.iter_mut().enumerate() {
+                if let Some((_, current)) = *x {
+                    let d = distance(next, j);
+                    if d < current {
+                        *x = Some((next, d));
+                    }
+                }
+            }
+        }
+        trace::debug!(edges = edges.len(), "built minimum spanning tree");
+        op.finish(self);
+        edges
+    }
+
+    /// Returns the ID and codon index of every stop codon found before the
+    /// last non-gap codon of its record. The alignment must be codon-aligned,
+    /// each codon spanning three columns from the first one; an incomplete
+    /// trailing codon is ignored, and so are codons made only of gaps.
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ATGTAAGGCTAA");
+    /// msa.push_record("id2", "ATGAAAGGCTAA");
+    /// let stops = msa.internal_stop_codons(&CodonTable::standard());
+    /// assert_eq!(stops, vec![("id1".to_string(), 1)]);
+    /// ```
+    pub fn internal_stop_codons(&self, table: &CodonTable) -> Vec<(String, usize)> {
+        let mut stops = Vec::new();
+        for record in &self.records {
+            let codons: Vec<u8> = record
+                .sequence()
+                .as_bytes()
+                .chunks_exact(3)
+                .map(|codon| table.translate_with(codon, &self.gaps))
+                .collect();
+            let last = match codons.iter().rposition(|&x| x != b'-') {
+                Some(last) => last,
+                None => continue,
+            };
+            stops.extend(
+                codons[..last]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &x)| x == b'*')
+                    .map(|(i, _)| (record.id().to_string(), i)),
+            );
+        }
+        stops
+    }
+
+    /// Translates every record with `table` (see [`Record::translate`]) into
+    /// a protein alignment a third as wide. Each column annotation keeps the
+    /// first character of every codon, the record annotations are dropped.
+    /// The alignment must be codon-aligned: rectangular, a multiple of three
+    /// columns wide, with no codon mixing gaps and nucleotides.
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ATG---");
+    /// msa.push_record("id2", "ATGTGG");
+    /// let protein = msa.translate(&CodonTable::standard()).unwrap();
+    /// assert_eq!(protein.get(0).unwrap().sequence(), "M-");
+    /// assert_eq!(protein.get(1).unwrap().sequence(), "MW");
+    /// ```
+    pub fn translate(&self, table: &CodonTable) -> Result<MSA, Error> {
+        self.validate()?;
+        for record in &self.records {
+            let broken = |codon: usize, message: String| Error::BrokenReadingFrame {
+                id: record.id().to_string(),
+                codon,
+                message,
+            };
+            if record.len() % 3 != 0 {
+                return Err(broken(
+                    record.len() / 3,
+                    format!("{} columns are not a multiple of three", record.len()),
+                ));
+            }
+            for (i, codon) in record.sequence().as_bytes().chunks_exact(3).enumerate() {
+                let gaps = codon.iter().filter(|x| self.gaps.is_gap(x)).count();
+                if gaps != 0 && gaps != 3 {
+                    return Err(broken(
+                        i,
+                        format!(
+                            "codon {} mixes gaps and nucleotides",
+                            String::from_utf8_lossy(codon)
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(MSA {
+            records: self
+                .records
+                .iter()
+                .map(|x| x.translate_with(table, &self.gaps))
+                .collect(),
+            annotations: self.annotations.clone(),
+            column_annotations: self
+                .column_annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.chars().step_by(3).collect()))
+                .collect(),
+            column_scores: self
+                .column_scores
+                .iter()
+                .map(|(k, v)| (k.clone(), v.iter().step_by(3).copied().collect()))
+                .collect(),
+            gaps: self.gaps.clone(),
+        })
+    }
+
+    /// Back-translates the protein alignment into a codon alignment, as
+    /// pal2nal does: each residue of a record is replaced by the next codon
+    /// of the coding sequence of the same ID in `nucleotides`, and each gap
+    /// by `---`. Gaps in the coding sequences are ignored, and a codon left
+    /// over after the last residue, such as a stop codon, is dropped. Column
+    /// annotations are repeated for the three columns of each codon, record
+    /// annotations are dropped.
+    ///
+    /// Fails if a record has no coding sequence, or if the coding sequence
+    /// length is neither three times the number of residues nor one codon
+    /// more.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    /// use align_rs::record::Record;
+    ///
+    /// let mut protein = MSA::default();
+    /// protein.push_record("id1", "M-K");
+    /// let cds = vec![Record::new("id1", "ATGAAATAA")];
+    /// let codons = protein.backtranslate(&cds).unwrap();
+    /// assert_eq!(codons.get(0).unwrap().sequence(), "ATG---AAA");
+    /// ```
+    pub fn backtranslate(&self, nucleotides: &[Record]) -> Result<MSA, Error> {
+        let mut records = Vec::with_capacity(self.len());
+        for record in &self.records {
+            let cds = nucleotides
+                .iter()
+                .find(|x| x.id() == record.id())
+                .ok_or_else(|| Error::RecordNotFound {
+                    id: record.id().to_string(),
+                })?
+                .ungapped_with(&self.gaps);
+            let residues = record
+                .sequence()
+                .bytes()
+                .filter(|x| !self.gaps.is_gap(x))
+                .count();
+            if cds.len() != residues * 3 && cds.len() != (residues + 1) * 3 {
+                return Err(Error::IncompatibleRecord {
+                    id: record.id().to_string(),
+                    message: format!(
+                        "{} residues cannot be encoded by {} nucleotides",
+                        residues,
+                        cds.len()
+                    ),
+                });
+            }
+
+            let mut codons = cds.as_bytes().chunks_exact(3);
+            let mut sequence = String::with_capacity(record.len() * 3);
+            for residue in record.sequence().bytes() {
+                match self.gaps.is_gap(&residue) {
+                    true => sequence.push_str("---"),
+                    false => sequence.push_str(&String::from_utf8_lossy(codons.next().unwrap())),
+                }
+            }
+            records.push(Record::new(record.id(), &sequence));
+        }
+        Ok(MSA {
+            records,
+            annotations: self.annotations.clone(),
+            column_annotations: self
+                .column_annotations
+                .iter()
+                .map(|(k, v)| (k.clone(), v.chars().flat_map(|c| [c, c, c]).collect()))
+                .collect(),
+            column_scores: self
+                .column_scores
+                .iter()
+                .map(|(k, v)| (k.clone(), v.iter().flat_map(|&x| [x, x, x]).collect()))
+                .collect(),
+            gaps: self.gaps.clone(),
+        })
+    }
+
+    /// Checks that the ungapped sequence of record `reference_id` is an open
+    /// reading frame: its length is a multiple of three and no stop codon
+    /// occurs before the last codon. Errors with the offending codon index
+    /// otherwise, or if there is no such record.
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("ref", "ATG---GCCTAA");
+    /// msa.push_record("id2", "ATGTAAGCCTAA");
+    /// let table = CodonTable::standard();
+    /// assert!(msa.check_reading_frame("ref", &table).is_ok());
+    /// assert!(msa.check_reading_frame("id2", &table).is_err());
+    /// ```
+    pub fn check_reading_frame(&self, reference_id: &str, table: &CodonTable) -> Result<(), Error> {
+        let record = self
+            .get_by_id(reference_id)
+            .ok_or_else(|| Error::RecordNotFound {
+                id: reference_id.to_string(),
+            })?;
+        let sequence = record.ungapped_with(&self.gaps);
+        let broken = |codon: usize, message: String| Error::BrokenReadingFrame {
+            id: reference_id.to_string(),
+            codon,
+            message,
+        };
+        if sequence.len() % 3 != 0 {
+            return Err(broken(
+                sequence.len() / 3,
+                format!("length {} is not a multiple of three", sequence.len()),
+            ));
+        }
+        let codons: Vec<&[u8]> = sequence.as_bytes().chunks_exact(3).collect();
+        for (i, codon) in codons
+            .iter()
+            .enumerate()
+            .take(codons.len().saturating_sub(1))
+        {
+            if table.is_stop(codon) {
+                return Err(broken(
+                    i,
+                    format!("internal stop codon {}", String::from_utf8_lossy(codon)),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the consensus sequence of the alignment using `X` as placeholder.
+    /// See [`MSA::consensus_with`].
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACG-");
+    /// msa.push_record("id2", "ACT-");
+    /// msa.push_record("id3", "AGT-");
+    /// assert_eq!(msa.consensus(0.6), "ACTX");
+    /// ```
+    pub fn consensus(&self, threshold: f64) -> String {
+        self.consensus_with(threshold, 'X')
+    }
+
+    /// Returns the consensus sequence of the alignment with the given
+    /// `threshold` and `placeholder`, other options left to their defaults.
+    /// See [`ConsensusOptions`].
+    pub fn consensus_with(&self, threshold: f64, placeholder: char) -> String {
+        self.consensus_with_options(&ConsensusOptions::new(threshold).placeholder(placeholder))
+    }
+
+    /// Returns the consensus sequence of the alignment: for each column, the
+    /// most frequent residue (case-insensitive, reported in uppercase) if its
+    /// frequency meets the threshold, the placeholder otherwise. All-gap
+    /// columns give the placeholder and an empty alignment an empty string.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::{ConsensusOptions, MSA};
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "aC");
+    /// msa.push_record("id2", "AG");
+    /// msa.push_record("id3", "--");
+    /// let options = ConsensusOptions::new(0.5).ties_to_placeholder(true);
+    /// assert_eq!(msa.consensus_with_options(&options), "AX");
+    /// let options = options.count_gaps(true);
+    /// assert_eq!(msa.consensus_with_options(&options), "AX");
+    /// ```
+    pub fn consensus_with_options(&self, options: &ConsensusOptions) -> String {
+        self.column_counts(false)
+            .iter()
+            .map(|counts| {
+                let mut gaps = 0;
+                let mut residues: HashMap<u8, usize> = HashMap::new();
+                for (&residue, &n) in counts {
+                    match residue {
+                        b'-' => gaps += n,
+                        _ => *residues.entry(residue.to_ascii_uppercase()).or_insert(0) += n,
+                    }
+                }
+
+                let mut total: usize = residues.values().sum();
+                if options.count_gaps {
+                    total += gaps;
+                }
+                let best = match residues.values().max() {
+                    Some(&best) if best as f64 / total as f64 >= options.threshold => best,
+                    _ => return options.placeholder,
+                };
+
+                // Ties are resolved deterministically
+                let mut tied: Vec<u8> = residues
+                    .iter()
+                    .filter(|(_, &n)| n == best)
+                    .map(|(&residue, _)| residue)
+                    .collect();
+                tied.sort_unstable();
+                match tied.len() {
+                    1 => tied[0] as char,
+                    _ if options.ties_to_placeholder => options.placeholder,
+                    _ => tied[0] as char,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns, for each record, the fraction of the `motif_columns` where it
+    /// carries the majority consensus residue (case-insensitive). Columns out
+    /// of range count as mismatches; an empty motif gives `0.0`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "CGHC");
+    /// msa.push_record("id2", "CGHC");
+    /// msa.push_record("id3", "CGHS");
+    /// let coverage = msa.motif_coverage(&[0, 3]);
+    /// assert_eq!(coverage[2], ("id3".to_string(), 0.5));
+    /// ```
+    pub fn motif_coverage(&self, motif_columns: &[usize]) -> Vec<(String, f64)> {
+        let consensus = self.consensus(0.0).into_bytes();
+        self.records
+            .iter()
+            .map(|x| {
+                let sequence = x.sequence().as_bytes();
+                let matches = motif_columns
+                    .iter()
+                    .filter(|&&col| match (sequence.get(col), consensus.get(col)) {
+                        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                        _ => false,
+                    })
+                    .count();
+                let coverage = if motif_columns.is_empty() {
+                    0.0
+                } else {
+                    matches as f64 / motif_columns.len() as f64
+                };
+                (x.id().to_string(), coverage)
+            })
+            .collect()
+    }
+
+    /// Store the consensus computed with `options` as the `consensus` column
+    /// annotation, replacing any previous value
+    pub fn annotate_consensus(&mut self, options: &ConsensusOptions) {
+        let consensus = self.consensus_with_options(options);
+        self.column_annotations
+            .insert("consensus".to_string(), consensus);
+    }
+
+    /// Returns a copy of the alignment where low-complexity stretches are
+    /// masked: every window of `window` columns whose consensus holds fewer
+    /// than `min_distinct` distinct residues has its residues replaced by
+    /// `mask_char` in every record. Gaps are left untouched.
+    pub fn mask_low_complexity(&self, window: usize, min_distinct: usize, mask_char: char) -> MSA {
+        let consensus = self.consensus(0.0).into_bytes();
+        let mut masked = vec![false; consensus.len()];
+        if window > 0 {
+            for (start, chunk) in consensus
+                .windows(window.min(consensus.len()).max(1))
+                .enumerate()
+            {
+                let distinct: HashSet<&u8> = chunk.iter().collect();
+                if distinct.len() < min_distinct {
+                    masked[start..start + chunk.len()].fill(true);
+                }
+            }
+        }
+
+        let mut msa = self.clone();
+        msa.records = self
+            .records
+            .iter()
+            .map(|x| {
+                let sequence = x
+                    .sequence()
+                    .chars()
+                    .zip(masked.iter().chain(std::iter::repeat(&false)))
+                    .map(|(c, &m)| {
+                        if m && !self.gaps.contains(c) {
+                            mask_char
+                        } else {
+                            c
+                        }
+                    })
+                    .collect();
+                x.with_sequence(sequence)
+            })
+            .collect();
+        msa
+    }
+
+    /// Replaces the residues of `columns` with `mask_char` in every record,
+    /// keeping gaps so that the gap structure is preserved. The same
+    /// positions of column annotations are blanked with a space.
+    ///
+    /// Fails with [`Error::ColumnOutOfRange`], leaving the alignment
+    /// untouched, if a column is past the end of the alignment.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "A-GT");
+    /// msa.mask_columns(&[1, 2], 'N').unwrap();
+    /// assert_eq!(msa.get(1).unwrap().sequence(), "A-NT");
+    /// assert!(msa.mask_columns(&[4], 'N').is_err());
+    /// ```
+    pub fn mask_columns(&mut self, columns: &[usize], mask_char: char) -> Result<(), Error> {
+        self.mask_columns_with(columns, mask_char, ' ')
+    }
+
+    /// Masks `columns` like [`MSA::mask_columns`], blanking column
+    /// annotations with `track_char`
+    pub fn mask_columns_with(
+        &mut self,
+        columns: &[usize],
+        mask_char: char,
+        track_char: char,
+    ) -> Result<(), Error> {
+        let len = self.col_len();
+        if let Some(&column) = columns.iter().find(|&&x| x >= len) {
+            return Err(Error::ColumnOutOfRange { column, len });
+        }
+
+        let mask = |s: &str, keep_gaps: bool, c: char| -> String {
+            let mut chars: Vec<char> = s.chars().collect();
+            for &column in columns {
+                match chars.get_mut(column) {
+                    Some(x) if keep_gaps && self.gaps.contains(*x) => {}
+                    Some(x) => *x = c,
+                    None => {}
+                }
+            }
+            chars.into_iter().collect()
+        };
+        for record in self.records.iter_mut() {
+            *record = record.with_sequence(mask(record.sequence(), true, mask_char));
+        }
+        for value in self.column_annotations.values_mut() {
+            *value = mask(value, false, track_char);
+        }
+        Ok(())
+    }
+
+    /// Masks the columns of `range` like [`MSA::mask_columns`]
+    pub fn mask_range(&mut self, range: Range<usize>, mask_char: char) -> Result<(), Error> {
+        self.mask_columns(&range.collect::<Vec<_>>(), mask_char)
+    }
+
+    /// Returns a copy of the alignment with `columns` masked like
+    /// [`MSA::mask_columns`]
+    pub fn masked_copy(&self, columns: &[usize], mask_char: char) -> Result<MSA, Error> {
+        let mut msa = self.clone();
+        msa.mask_columns(columns, mask_char)?;
+        Ok(msa)
+    }
+
+    /// Returns a consensus where a column becomes a gap (`-`) only if gaps are
+    /// the strict majority of the column; otherwise the most frequent non-gap
+    /// residue is used, ties going to the residue that sorts first.
+    pub fn gap_aware_consensus(&self) -> String {
+        self.column_counts(false)
+            .iter()
+            .map(|counts| {
+                let (gaps, residues): (Vec<_>, Vec<_>) =
+                    counts.iter().partition(|(&b, _)| b == b'-');
+                let gaps: usize = gaps.iter().map(|(_, &n)| n).sum();
+                let total: usize = counts.values().sum();
+                if gaps * 2 > total {
+                    return '-';
+                }
+                residues
+                    .into_iter()
+                    .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+                    .map(|(&residue, _)| residue as char)
+                    .unwrap_or('-')
+            })
+            .collect()
+    }
+
+    /// Returns the default consensus (see [`ConsensusOptions`]) of every
+    /// window of `window` columns, with its start column, the windows
+    /// starting every `step` columns. Windows running past the last column
+    /// are truncated.
+    ///
+    /// # Panics
+    /// Panics if `step` is 0.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGTA");
+    /// msa.push_record("id2", "ACGTA");
+    /// assert_eq!(
+    ///     msa.window_consensus(3, 2),
+    ///     vec![(0, "ACG".to_string()), (2, "GTA".to_string()), (4, "A".to_string())]
+    /// );
+    /// ```
+    pub fn window_consensus(&self, window: usize, step: usize) -> Vec<(usize, String)> {
+        let consensus: Vec<char> = self
+            .consensus_with_options(&ConsensusOptions::default())
+            .chars()
+            .collect();
+        if window == 0 {
+            return Vec::new();
+        }
+        (0..consensus.len())
+            .step_by(step)
+            .map(|start| {
+                let end = std::cmp::min(start + window, consensus.len());
+                (start, consensus[start..end].iter().collect())
+            })
+            .collect()
+    }
+
+    /// Returns a consensus made of the most frequent non-gap residue of each
+    /// column whose fraction of non-gap records is at least `min_occupancy`,
+    /// ties going to the residue that sorts first. Less occupied columns are
+    /// written as `absent_char`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACG");
+    /// msa.push_record("id2", "AC-");
+    /// msa.push_record("id3", "AT-");
+    /// assert_eq!(msa.occupancy_consensus(0.5, '?'), "AC?");
+    /// ```
+    pub fn occupancy_consensus(&self, min_occupancy: f64, absent_char: char) -> String {
+        self.column_counts(true)
+            .iter()
+            .map(|counts| {
+                let occupancy = counts.values().sum::<usize>() as f64 / self.len() as f64;
+                if occupancy < min_occupancy {
+                    return absent_char;
+                }
+                counts
+                    .iter()
+                    .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+                    .map(|(&residue, _)| residue as char)
+                    .unwrap_or(absent_char)
+            })
+            .collect()
+    }
+
+    /// Returns the mutual information (in bits) between each column and the
+    /// next, so the vector has one element less than `col_len()`. With
+    /// `ignore_gaps`, records with a gap in either column are left out;
+    /// otherwise gaps count as one more symbol.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACA");
+    /// msa.push_record("id2", "GTA");
+    /// assert_eq!(msa.adjacent_column_mi(true), vec![1.0, 0.0]);
+    /// ```
+    pub fn adjacent_column_mi(&self, ignore_gaps: bool) -> Vec<f64> {
+        (1..self.col_len())
+            .map(|col| self.mutual_information(col - 1, col, ignore_gaps))
+            .collect()
+    }
+
+    /// Mutual information in bits between columns `a` and `b`
+    fn mutual_information(&self, a: usize, b: usize, ignore_gaps: bool) -> f64 {
+        let symbol = |residue: u8| match self.gaps.is_gap(&residue) {
+            true if ignore_gaps => None,
+            true => Some(b'-'),
+            false => Some(residue),
+        };
+        let mut joint: HashMap<(u8, u8), usize> = HashMap::new();
+        for record in &self.records {
+            let bytes = record.sequence().as_bytes();
+            let pair = (bytes.get(a).copied(), bytes.get(b).copied());
+            if let (Some(x), Some(y)) = pair {
+                if let (Some(x), Some(y)) = (symbol(x), symbol(y)) {
+                    *joint.entry((x, y)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let total: usize = joint.values().sum();
+        let mut left: HashMap<u8, usize> = HashMap::new();
+        let mut right: HashMap<u8, usize> = HashMap::new();
+        for (&(x, y), &n) in &joint {
+            *left.entry(x).or_insert(0) += n;
+            *right.entry(y).or_insert(0) += n;
+        }
+        joint
+            .iter()
+            .map(|(&(x, y), &n)| {
+                let p = n as f64 / total as f64;
+                let expected = (left[&x] * right[&y]) as f64 / (total * total) as f64;
+                p * (p / expected).log2()
+            })
+            .sum::<f64>()
+            .max(0.0)
+    }
+
+    /// Returns the Shannon entropy (base 2) of the residue distribution of
+    /// each column. Gaps are ignored when `ignore_gaps` is `true`, otherwise
+    /// they count as one more symbol. Fully conserved columns give `0.0`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AA");
+    /// msa.push_record("id2", "A-");
+    /// assert_eq!(msa.column_entropy(true), vec![0.0, 0.0]);
+    /// assert_eq!(msa.column_entropy(false), vec![0.0, 1.0]);
+    /// ```
+    pub fn column_entropy(&self, ignore_gaps: bool) -> Vec<f64> {
+        self.entropies(2.0, ignore_gaps)
+    }
+
+    /// Returns the frequency of each non-gap residue at column `col`. The map
+    /// is empty for an all-gap or out-of-range column.
+    pub fn column_frequencies(&self, col: usize) -> HashMap<char, f64> {
+        let mut counts: HashMap<u8, usize> = HashMap::new();
+        for record in &self.records {
+            match record.sequence().as_bytes().get(col) {
+                Some(residue) if !self.gaps.is_gap(residue) => {
+                    *counts.entry(*residue).or_insert(0) += 1
+                }
+                _ => {}
+            }
+        }
+        let total: usize = counts.values().sum();
+        counts
+            .into_iter()
+            .map(|(residue, n)| (residue as char, n as f64 / total as f64))
+            .collect()
+    }
+
+    /// Returns, for each column, the frequency of the most common non-gap
+    /// residue among non-gap residues. All-gap columns give `0.0`.
+    pub fn conservation(&self) -> Vec<f64> {
+        self.column_counts(true)
+            .iter()
+            .map(|counts| {
+                let total: usize = counts.values().sum();
+                match counts.values().max() {
+                    Some(&best) => best as f64 / total as f64,
+                    None => 0.0,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the conservation score of Valdar and Thornton (2001) of each
+    /// column: the weighted mean, over all pairs of distinct records, of the
+    /// substitution score of their residues. Scores are rescaled to [0, 1]
+    /// with the extreme scores of `matrix` over the letters and `*`, and a
+    /// pair involving a gap scores 0. `weights` pairs record IDs with their
+    /// weight; with duplicate IDs the first pair is used. Columns give `0.0`
+    /// with fewer than two weighted records.
+    ///
+    /// # Panics
+    /// Panics if a record has no weight in `weights`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    /// use align_rs::scoring::ScoringMatrix;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AA");
+    /// msa.push_record("id2", "AC");
+    /// let weights = vec![("id1".to_string(), 1.0), ("id2".to_string(), 1.0)];
+    /// let scores = msa.valdar_conservation(&ScoringMatrix::new(1, 0, 0), &weights);
+    /// assert_eq!(scores, vec![1.0, 0.0]);
+    /// ```
+    pub fn valdar_conservation(
+        &self,
+        matrix: &ScoringMatrix,
+        weights: &[(String, f64)],
+    ) -> Vec<f64> {
+        let alphabet: Vec<u8> = (b'A'..=b'Z').chain(std::iter::once(b'*')).collect();
+        let (mut min, mut max) = (i32::MAX, i32::MIN);
+        for &a in &alphabet {
+            for &b in &alphabet {
+                min = min.min(matrix.score(a, b));
+                max = max.max(matrix.score(a, b));
+            }
+        }
+        let mutation = |a: u8, b: u8| match max > min {
+            true => (matrix.score(a, b) - min) as f64 / (max - min) as f64,
+            false => 1.0,
+        };
+
+        let record_weights: Vec<f64> = self
+            .records
+            .iter()
+            .map(|record| {
+                weights
+                    .iter()
+                    .find(|(id, _)| id == record.id())
+                    .map(|&(_, w)| w)
+                    .unwrap_or_else(|| panic!("no weight for record {}", record.id()))
+            })
+            .collect();
+        let total: f64 = record_weights.iter().sum();
+        let squares: f64 = record_weights.iter().map(|w| w * w).sum();
+        let pairs = total * total - squares;
+
+        (0..self.col_len())
+            .map(|col| {
+                if pairs <= 0.0 {
+                    return 0.0;
+                }
+                // Weighted frequencies of the residues, and the self pairs
+                // to take out of their products
+                let mut frequencies: HashMap<u8, f64> = HashMap::new();
+                let mut self_pairs = 0.0;
+                for (record, &w) in self.records.iter().zip(&record_weights) {
+                    match record.sequence().as_bytes().get(col) {
+                        Some(&residue) if !self.gaps.is_gap(&residue) => {
+                            let residue = residue.to_ascii_uppercase();
+                            *frequencies.entry(residue).or_insert(0.0) += w;
+                            self_pairs += w * w * mutation(residue, residue);
+                        }
+                        _ => {}
+                    }
+                }
+                let mut score = -self_pairs;
+                for (&a, &wa) in &frequencies {
+                    for (&b, &wb) in &frequencies {
+                        score += wa * wb * mutation(a, b);
+                    }
+                }
+                score / pairs
+            })
+            .collect()
+    }
+
+    /// Sets the "cons" column annotation, replacing any previous one, to the
+    /// CLUSTAL conservation line: `*` for a column of identical residues
+    /// (case insensitive), `:` if all its residues belong to one of the
+    /// [`STRONG_GROUPS`], `.` to one of the [`WEAK_GROUPS`], and a space
+    /// otherwise or if it holds a gap.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "MSSAW");
+    /// msa.push_record("id2", "MTGA-");
+    /// msa.compute_conservation();
+    /// assert_eq!(msa.get_column_annotation("cons").unwrap(), "*:.* ");
+    /// ```
+    pub fn compute_conservation(&mut self) {
+        let cons: String = (0..self.col_len())
+            .map(|col| {
+                let mut residues = HashSet::new();
+                for record in &self.records {
+                    match record.sequence().as_bytes().get(col) {
+                        Some(b) if !self.gaps.is_gap(b) => {
+                            residues.insert(b.to_ascii_uppercase());
+                        }
+                        _ => return ' ',
+                    }
+                }
+                let within = |groups: &[&str]| {
+                    groups
+                        .iter()
+                        .any(|group| residues.iter().all(|x| group.as_bytes().contains(x)))
+                };
+                match residues.len() {
+                    0 => ' ',
+                    1 => '*',
+                    _ if within(&STRONG_GROUPS) => ':',
+                    _ if within(&WEAK_GROUPS) => '.',
+                    _ => ' ',
+                }
+            })
+            .collect();
+        self.column_scores.remove("cons");
+        self.column_annotations.insert("cons".to_string(), cons);
+    }
+
+    /// Returns, for each column, the variance of the residue volumes (in
+    /// cubic angstroms, Zamyatnin 1972) over the non-gap residues. Residues
+    /// other than the 20 standard amino acids are ignored; columns without
+    /// any standard residue give `0.0`.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "GA");
+    /// msa.push_record("id2", "WA");
+    /// let variance = msa.volume_variance();
+    /// assert!(variance[0] > variance[1]);
+    /// assert_eq!(variance[1], 0.0);
+    /// ```
+    pub fn volume_variance(&self) -> Vec<f64> {
+        self.column_counts(true)
+            .iter()
+            .map(|counts| {
+                let volumes: Vec<(f64, f64)> = counts
+                    .iter()
+                    .filter_map(|(&residue, &n)| Some((residue_volume(residue)?, n as f64)))
+                    .collect();
+                let total: f64 = volumes.iter().map(|(_, n)| n).sum();
+                if total == 0.0 {
+                    return 0.0;
+                }
+                let mean = volumes.iter().map(|(v, n)| v * n).sum::<f64>() / total;
+                volumes
+                    .iter()
+                    .map(|(v, n)| n * (v - mean).powi(2))
+                    .sum::<f64>()
+                    / total
+            })
+            .collect()
+    }
+
+    /// Returns the Shannon entropy of each column in bits (log2), gaps
+    /// excluded. All-gap columns give `0.0`. Same as `column_entropy(true)`.
+    pub fn entropy(&self) -> Vec<f64> {
+        self.column_entropy(true)
+    }
+
+    /// Shannon entropy of each column in the given logarithm `base`
+    fn entropies(&self, base: f64, ignore_gaps: bool) -> Vec<f64> {
+        self.column_counts(ignore_gaps)
+            .iter()
+            .map(|counts| {
+                let total: usize = counts.values().sum();
+                counts
+                    .values()
+                    .map(|&n| {
+                        let p = n as f64 / total as f64;
+                        -p * p.log(base)
+                    })
+                    .sum::<f64>()
+            })
+            .collect()
+    }
+
+    /// Returns the first derivative of the per-column entropy, after smoothing
+    /// the entropy with a centered moving average of `smoothing` columns.
+    /// Peaks in absolute value mark transitions between conserved and variable
+    /// regions. A `smoothing` of 0 or 1 disables smoothing.
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AAAC");
+    /// msa.push_record("id2", "AAAG");
+    /// let gradient = msa.entropy_gradient(2.0, 1);
+    /// assert_eq!(gradient, vec![0.0, 0.0, 0.5, 1.0]);
+    /// ```
+    pub fn entropy_gradient(&self, base: f64, smoothing: usize) -> Vec<f64> {
+        let entropy = self.entropies(base, true);
+        let n = entropy.len();
+        let half = smoothing / 2;
+
+        let smoothed: Vec<f64> = (0..n)
+            .map(|i| {
+                let lo = i.saturating_sub(half);
+                let hi = std::cmp::min(n, lo + smoothing.max(1));
+                entropy[lo..hi].iter().sum::<f64>() / (hi - lo) as f64
+            })
+            .collect();
+
+        // Central differences inside, one-sided differences at both ends
+        (0..n)
+            .map(|i| match i {
+                _ if n < 2 => 0.0,
+                0 => smoothed[1] - smoothed[0],
+                _ if i == n - 1 => smoothed[i] - smoothed[i - 1],
+                _ => (smoothed[i + 1] - smoothed[i - 1]) / 2.0,
+            })
+            .collect()
+    }
+
+    /// Returns summary statistics of the alignment as a JSON object with the
+    /// keys `num_rows`, `num_columns`, `gap_fraction` (over all cells),
+    /// `mean_identity` (mean of [`MSA::identity_matrix`] over distinct pairs,
+    /// `null` with fewer than two records), `conservation` and `entropy`
+    /// (see [`MSA::conservation`] and [`MSA::entropy`]) and `alphabet`, one
+    /// of `"dna"`, `"rna"`, `"protein"` or `"unknown"` (see
+    /// [`MSA::guess_alphabet`]).
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC");
+    /// assert_eq!(
+    ///     msa.stats_json(),
+    ///     r#"{"num_rows":1,"num_columns":2,"gap_fraction":0,"mean_identity":null,"conservation":[1,1],"entropy":[0,0],"alphabet":"dna"}"#
+    /// );
+    /// ```
+    pub fn stats_json(&self) -> String {
+        let summary = self.summary();
+        let mean_identity = summary
+            .mean_identity()
+            .map_or_else(|| "null".to_string(), json_number);
+        let array = |values: Vec<f64>| -> String {
+            let values: Vec<String> = values.into_iter().map(json_number).collect();
+            format!("[{}]", values.join(","))
+        };
+        format!(
+            "{{\"num_rows\":{},\"num_columns\":{},\"gap_fraction\":{},\"mean_identity\":{},\"conservation\":{},\"entropy\":{},\"alphabet\":\"{}\"}}",
+            summary.num_rows(),
+            summary.num_columns(),
+            json_number(summary.gap_fraction()),
+            mean_identity,
+            array(self.conservation()),
+            array(self.entropy()),
+            summary.alphabet()
+        )
+    }
+
+    /// Returns the headline numbers of the alignment, see [`AlignmentSummary`]
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "AC-T");
+    /// msa.push_record("id2", "AGGT");
+    /// let summary = msa.summary();
+    /// assert_eq!(summary.num_columns(), 4);
+    /// assert_eq!(summary.gap_fraction(), 0.125);
+    /// assert_eq!(summary.mean_identity(), Some(2.0 / 3.0));
+    /// ```
+    pub fn summary(&self) -> AlignmentSummary {
+        let gap_fractions = self.gap_fractions();
+        let gap_fraction = if gap_fractions.is_empty() {
+            0.0
+        } else {
+            gap_fractions.iter().sum::<f64>() / gap_fractions.len() as f64
+        };
+        let identities: Vec<f64> = self
+            .identity_matrix()
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| row[i + 1..].to_vec())
+            .collect();
+        let mean_identity = if identities.is_empty() {
+            None
+        } else {
+            Some(identities.iter().sum::<f64>() / identities.len() as f64)
+        };
+        AlignmentSummary {
+            num_rows: self.len(),
+            num_columns: self.col_len(),
+            gap_fraction,
+            mean_identity,
+            alphabet: self.guess_alphabet(),
+        }
+    }
+
+    /// Returns the QC statistics of the alignment, see [`MsaStats`]. The mean
+    /// pairwise identity is computed on at most 1000 records, see
+    /// [`MSA::stats_sampled`].
+    /// # Example
+    /// ```
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGT");
+    /// msa.push_record("id2", "AC-A");
+    /// let stats = msa.stats();
+    /// assert_eq!(stats.gap_fraction(), 0.125);
+    /// assert_eq!(stats.conserved_columns(), 2);
+    /// assert_eq!(stats.mean_identity(), Some(2.0 / 3.0));
+    /// ```
+    pub fn stats(&self) -> MsaStats {
+        self.stats_sampled(Some(STATS_IDENTITY_SAMPLE))
+    }
+
+    /// Returns the QC statistics of the alignment, computing the mean
+    /// pairwise identity on at most `sample` records evenly spread over the
+    /// alignment, or on all of them with `None`
+    pub fn stats_sampled(&self, sample: Option<usize>) -> MsaStats {
+        let cells = self.len() * self.col_len();
+        let record_gap_fractions: Vec<(String, f64)> = self
+            .records
+            .iter()
+            .map(|record| {
+                let gaps = record
+                    .sequence()
+                    .bytes()
+                    .filter(|x| self.gaps.is_gap(x))
+                    .count();
+                let fraction = match record.len() {
+                    0 => 0.0,
+                    len => gaps as f64 / len as f64,
+                };
+                (record.id().to_string(), fraction)
+            })
+            .collect();
+        let gaps: f64 = self
+            .records
+            .iter()
+            .zip(&record_gap_fractions)
+            .map(|(record, (_, fraction))| fraction * record.len() as f64)
+            .sum();
+        let gap_fraction = if cells == 0 { 0.0 } else { gaps / cells as f64 };
+
+        let conserved_columns = self
+            .column_counts(false)
+            .iter()
+            .filter(|counts| {
+                let mut residues = counts.keys().map(u8::to_ascii_uppercase);
+                let first = residues.next();
+                matches!(first, Some(x) if x != b'-' && residues.all(|y| y == x))
+            })
+            .count();
+
+        let n = self.len();
+        let sampled = sample.map_or(n, |sample| sample.min(n));
+        let records: Vec<&[u8]> = (0..sampled)
+            .map(|i| self.records[i * n / sampled].sequence().as_bytes())
+            .collect();
+        let mut identities = Vec::new();
+        for (i, a) in records.iter().enumerate() {
+            for b in &records[i + 1..] {
+                identities.push(pair_identity(a, b, &self.gaps, GapPolicy::default()));
+            }
+        }
+        let mean_identity = if identities.is_empty() {
+            None
+        } else {
+            Some(identities.iter().sum::<f64>() / identities.len() as f64)
+        };
+
+        MsaStats {
+            num_sequences: n,
+            alignment_length: self.col_len(),
+            gap_fraction,
+            record_gap_fractions,
+            conserved_columns,
+            mean_identity,
+        }
+    }
+
+    /// Guesses the alphabet of the alignment from its non-gap residues, see
+    /// [`Alphabet`]. An alignment without residues is `Alphabet::Unknown`.
+    /// # Example
+    /// ```
+    /// use align_rs::alphabet::Alphabet;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGU-");
+    /// assert_eq!(msa.guess_alphabet(), Alphabet::Rna);
+    /// ```
+    pub fn guess_alphabet(&self) -> Alphabet {
+        self.guess_alphabet_sampled(usize::MAX)
+    }
+
+    /// Guesses the alphabet like [`MSA::guess_alphabet`] from at most
+    /// `max_residues` non-gap residues, shared evenly between the records
+    /// and taken from the start of each
+    /// # Example
+    /// ```
+    /// use align_rs::alphabet::Alphabet;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACGTACGTMKLV");
+    /// msa.push_record("id2", "ACGTACGTACGT");
+    /// assert_eq!(msa.guess_alphabet(), Alphabet::Protein);
+    /// assert_eq!(msa.guess_alphabet_sampled(16), Alphabet::Dna);
+    /// ```
+    pub fn guess_alphabet_sampled(&self, max_residues: usize) -> Alphabet {
+        let per_record = match self.len() {
+            0 => 0,
+            n => max_residues.saturating_add(n - 1) / n,
+        };
+        Alphabet::guess(
+            self.records
+                .iter()
+                .flat_map(|x| {
+                    x.sequence()
+                        .bytes()
+                        .filter(|x| !self.gaps.is_gap(x))
+                        .take(per_record)
+                })
+                .take(max_residues),
+        )
+    }
+
+    /// Checks that every non-gap character belongs to `alphabet`. The error
+    /// reports the record, column and character of the first offender.
+    /// # Example
+    /// ```
+    /// use align_rs::alphabet::Alphabet;
+    /// use align_rs::msa::MSA;
+    ///
+    /// let mut msa = MSA::default();
+    /// msa.push_record("id1", "ACG-T");
+    /// msa.push_record("id2", "ACGJT");
+    /// assert_eq!(
+    ///     msa.validate_alphabet(Alphabet::Dna).unwrap_err().to_string(),
+    ///     "Sequence id2 has invalid residue 'J' at column 3"
+    /// );
+    /// ```
+    pub fn validate_alphabet(&self, alphabet: Alphabet) -> Result<(), Error> {
+        for record in &self.records {
+            let invalid = record.sequence().char_indices().find(|&(_, c)| {
+                !(self.gaps.contains(c) || (c.is_ascii() && alphabet.contains(c as u8)))
+            });
+            if let Some((col, residue)) = invalid {
+                return Err(Error::InvalidResidue {
+                    id: record.id().to_string(),
+                    column: col,
+                    residue,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format `value` as a JSON number, `null` if it is not finite. Negative
+/// zero is written as `0`.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        (value + 0.0).to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Values of a column annotation, one per column of the alignment
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnTrack {
+    /// One character per column, such as a conservation line
+    Chars(String),
+
+    /// One number per column, such as posterior probabilities
+    Scores(Vec<f64>),
+}
+
+impl ColumnTrack {
+    /// Returns the number of values, characters being counted as bytes like
+    /// the columns of records
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnTrack::Chars(value) => value.len(),
+            ColumnTrack::Scores(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Headline numbers of an alignment, returned by [`MSA::summary`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentSummary {
+    num_rows: usize,
+    num_columns: usize,
+
+    /// Mean of the column gap fractions
+    gap_fraction: f64,
+
+    /// Mean pairwise identity, `None` with fewer than two records
+    mean_identity: Option<f64>,
+
+    alphabet: Alphabet,
+}
+
+impl AlignmentSummary {
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.num_columns
+    }
+
+    /// Returns the mean of the column gap fractions, `0.0` without columns
+    pub fn gap_fraction(&self) -> f64 {
+        self.gap_fraction
+    }
+
+    /// Returns the mean pairwise identity (see [`MSA::identity_matrix`]),
+    /// `None` with fewer than two records
+    pub fn mean_identity(&self) -> Option<f64> {
+        self.mean_identity
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+}
+
+/// QC statistics of an alignment, returned by [`MSA::stats`]. The `Display`
+/// implementation prints one statistic per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsaStats {
+    num_sequences: usize,
+    alignment_length: usize,
+
+    /// Fraction of gaps over all the residues of the alignment
+    gap_fraction: f64,
+
+    /// Gap fraction of each record, with its ID
+    record_gap_fractions: Vec<(String, f64)>,
+
+    /// Number of gapless columns of a single residue
+    conserved_columns: usize,
+
+    /// Mean pairwise identity of the sampled records
+    mean_identity: Option<f64>,
+}
+
+impl MsaStats {
+    pub fn num_sequences(&self) -> usize {
+        self.num_sequences
+    }
+
+    pub fn alignment_length(&self) -> usize {
+        self.alignment_length
+    }
+
+    /// Returns the fraction of gaps over all the residues, `0.0` for an
+    /// empty alignment
+    pub fn gap_fraction(&self) -> f64 {
+        self.gap_fraction
+    }
+
+    /// Returns the gap fraction of each record with its ID, `0.0` for an
+    /// empty record
+    pub fn record_gap_fractions(&self) -> &[(String, f64)] {
+        &self.record_gap_fractions
+    }
+
+    /// Returns the number of gapless columns made of a single residue (case
+    /// insensitive)
+    pub fn conserved_columns(&self) -> usize {
+        self.conserved_columns
+    }
+
+    /// Returns the mean pairwise identity (see [`MSA::identity_matrix`]) of
+    /// the sampled records, `None` with fewer than two of them
+    pub fn mean_identity(&self) -> Option<f64> {
+        self.mean_identity
+    }
+}
+
+impl fmt::Display for MsaStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Sequences: {}", self.num_sequences)?;
+        writeln!(f, "Columns: {}", self.alignment_length)?;
+        writeln!(f, "Gap fraction: {:.4}", self.gap_fraction)?;
+        writeln!(f, "Conserved columns: {}", self.conserved_columns)?;
+        match self.mean_identity {
+            Some(identity) => writeln!(f, "Mean pairwise identity: {:.4}", identity)?,
+            None => writeln!(f, "Mean pairwise identity: n/a")?,
+        }
+        writeln!(f, "Gap fraction per sequence:")?;
+        for (id, fraction) in &self.record_gap_fractions {
+            writeln!(f, "  {}\t{:.4}", id, fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the consensus computation of [`MSA::consensus_with_options`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsensusOptions {
+    /// Minimal frequency of the most common residue
+    threshold: f64,
+
+    /// Character used when no residue reaches the threshold
+    placeholder: char,
+
+    /// Count gaps in the frequency denominator
+    count_gaps: bool,
+
+    /// Emit the placeholder instead of the first residue on ties
+    ties_to_placeholder: bool,
+}
+
+impl Default for ConsensusOptions {
+    fn default() -> Self {
+        ConsensusOptions {
+            threshold: 0.5,
+            placeholder: 'X',
+            count_gaps: false,
+            ties_to_placeholder: false,
+        }
+    }
+}
+
+impl ConsensusOptions {
+    /// Creates default options with the given frequency `threshold`
+    pub fn new(threshold: f64) -> Self {
+        ConsensusOptions {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Set the character used for columns without consensus (default `X`)
+    pub fn placeholder(mut self, placeholder: char) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Count gaps in the frequency denominator (default `false`)
+    pub fn count_gaps(mut self, count_gaps: bool) -> Self {
+        self.count_gaps = count_gaps;
+        self
+    }
+
+    /// On ties, emit the placeholder rather than the residue that sorts
+    /// first (default `false`)
+    pub fn ties_to_placeholder(mut self, ties_to_placeholder: bool) -> Self {
+        self.ties_to_placeholder = ties_to_placeholder;
+        self
+    }
+}
+
+/// Set of characters counted as gaps by an alignment (see
+/// [`MSA::set_gaps`]) and by the `_with` variants of gap-aware methods. The
+/// default holds `-` and `.`.
+/// # Example
+/// ```
+/// use align_rs::msa::GapSet;
+///
+/// let gaps = GapSet::new(&['-', '~']);
+/// assert!(gaps.contains('~'));
+/// assert!(!gaps.contains('.'));
+/// assert!(GapSet::default().contains('.'));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GapSet {
+    chars: Vec<char>,
+}
+
+impl GapSet {
+    pub fn new(chars: &[char]) -> Self {
+        GapSet {
+            chars: chars.to_vec(),
+        }
+    }
+
+    /// Returns `true` if `c` is a gap
+    pub fn contains(&self, c: char) -> bool {
+        self.chars.contains(&c)
+    }
+
+    /// Returns `true` if the residue byte `b` is a gap
+    pub(crate) fn is_gap(&self, b: &u8) -> bool {
+        self.contains(*b as char)
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+}
+
+impl From<&[char]> for GapSet {
+    fn from(chars: &[char]) -> Self {
+        GapSet::new(chars)
+    }
+}
+
+impl<const N: usize> From<[char; N]> for GapSet {
+    fn from(chars: [char; N]) -> Self {
+        GapSet::new(&chars)
+    }
+}
+
+impl Default for GapSet {
+    fn default() -> Self {
+        GapSet {
+            chars: GAP_CHARS.iter().map(|&b| b as char).collect(),
+        }
+    }
+}
+
+/// How identity computations treat a column where exactly one of the two
+/// records has a gap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// Skip the column
+    #[default]
+    Ignore,
+
+    /// Count the column as a mismatch
+    Mismatch,
+}
+
+/// Fraction of identical residues over the compared columns of `a` and `b`,
+/// `0.0` if there is no such column. Columns where both have a gap are never
+/// compared.
+fn pair_identity(a: &[u8], b: &[u8], gap_set: &GapSet, gaps: GapPolicy) -> f64 {
+    if a == b && a.iter().any(|x| !gap_set.is_gap(x)) {
+        return 1.0;
+    }
+    let (mut aligned, mut identical) = (0usize, 0usize);
+    for (x, y) in a.iter().zip(b) {
+        match (gap_set.is_gap(x), gap_set.is_gap(y)) {
+            (true, true) => continue,
+            (false, false) => {}
+            _ if gaps == GapPolicy::Ignore => continue,
+            _ => {
+                aligned += 1;
+                continue;
+            }
+        }
+        aligned += 1;
+        if x == y {
+            identical += 1;
+        }
+    }
+    if aligned == 0 {
+        0.0
+    } else {
+        identical as f64 / aligned as f64
+    }
+}
+
+/// Volume of a standard amino acid in cubic angstroms (Zamyatnin 1972),
+/// case insensitive
+fn residue_volume(residue: u8) -> Option<f64> {
+    let volume = match residue.to_ascii_uppercase() {
+        b'A' => 88.6,
+        b'R' => 173.4,
+        b'N' => 114.1,
+        b'D' => 111.1,
+        b'C' => 108.5,
+        b'Q' => 143.8,
+        b'E' => 138.4,
+        b'G' => 60.1,
+        b'H' => 153.2,
+        b'I' => 166.7,
+        b'L' => 166.7,
+        b'K' => 168.6,
+        b'M' => 162.9,
+        b'F' => 189.9,
+        b'P' => 112.7,
+        b'S' => 89.0,
+        b'T' => 116.1,
+        b'W' => 227.8,
+        b'Y' => 193.6,
+        b'V' => 140.0,
+        _ => return None,
+    };
+    Some(volume)
+}
+
+/// Sum of 1 / i for i in 1..=n
+fn harmonic(n: usize) -> f64 {
+    (1..=n).map(|i| 1.0 / i as f64).sum()
+}
+
+impl Index<usize> for MSA {
+    type Output = Record;
+
+    /// Returns the record at position `index`
+    ///
+    /// # Panics
+    /// Panics if `index` is not below `len()`.
+    fn index(&self, index: usize) -> &Record {
+        &self.records[index]
+    }
+}
+
+impl IntoIterator for MSA {
+    type Item = Record;
+    type IntoIter = std::vec::IntoIter<Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MSA {
+    type Item = &'a Record;
+    type IntoIter = std::slice::Iter<'a, Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut MSA {
+    type Item = &'a mut Record;
+    type IntoIter = std::slice::IterMut<'a, Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.iter_mut()
+    }
+}
+
+impl fmt::Display for MSA {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No sequence in alignment");
+        }
+
+        let num_rows = self.len();
+        let num_cols = self.col_len();
+        let mut string = String::new();
+        let mut to_continue: &str = "";
+        for (idx, record) in self.records.iter().enumerate() {
+            if idx > 9 {
+                to_continue = "...";
+                break;
+            }
+            // Truncate on characters, a byte index could split a multibyte one
+            let mut chars = record.sequence().chars();
+            let truncated_sequence: String = chars.by_ref().take(30).collect();
+            string.push_str(&format!(
+                "{}\t{}\n",
+                record.id(),
+                if chars.next().is_some() {
+                    format!("{}...", truncated_sequence)
+                } else {
+                    truncated_sequence
+                }
+            ));
+        }
+        let header = if num_rows == 1 {
+            format!("Alignment with {} row", num_rows)
+        } else {
+            format!("Alignment with {} rows", num_rows)
+        };
+
+        let column_desc = if num_cols == 1 {
+            format!(" and {} column", num_cols)
+        } else {
+            format!(" and {} columns", num_cols)
+        };
+        if to_continue.is_empty() {
+            write!(f, "{}{}\n{}", header, column_desc, string)
+        } else {
+            write!(f, "{}{}\n{}\n{}", header, column_desc, string, to_continue)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::Reader;
+    use crate::record::Record;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn msa_new() {
+        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+
+        let mut annotations = HashMap::new();
+        annotations.insert(String::from("author"), String::from("John Doe"));
+
+        let mut column_annotations = HashMap::new();
+        column_annotations.insert(String::from("1"), String::from("column 1"));
+
+        let msa = MSA::new(
+            records.clone(),
+            annotations.clone(),
+            column_annotations.clone(),
+        );
+
+        assert_eq!(msa.records, records);
+        assert_eq!(msa.annotations, annotations);
+        assert_eq!(msa.column_annotations, column_annotations);
+    }
+
+    #[test]
+    fn msa_len() {
+        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+
+        let msa = MSA::new(records.clone(), HashMap::new(), HashMap::new());
+        assert_eq!(msa.len(), 2);
+    }
+
+    #[test]
+    fn msa_alignment_len() {
+        let records = vec![Record::new("id1", "ACGT"), Record::new("id2", "TGCA")];
+
+        let msa = MSA::new(records.clone(), HashMap::new(), HashMap::new());
+        assert_eq!(msa.col_len(), 4);
+    }
+
+    #[test]
+    fn msa_push_valid_sequence() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        assert_eq!(msa.len(), 1);
+        assert_eq!(msa.records[0], Record::new("id1", "ACGT"));
+    }
+
+    #[test]
+    fn msa_print_no_seqs() {
+        let msa = MSA::default();
+        assert_eq!(msa.to_string(), "No sequence in alignment");
+    }
+    #[test]
+    fn msa_print_one_seqs() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACG");
+        assert_eq!(
+            msa.to_string(),
+            "Alignment with 1 row and 3 columns\nid1\tACG\n"
+        );
+    }
+
+    #[test]
+    fn msa_select_retain_remove() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        msa.push_record("id2", "AC-T");
+        msa.push_record("id3", "TCGT");
+        msa.add_column_annotation("cons", " * *");
+
+        let (subset, missing) = msa.select(&["id3", "id4", "id1"]);
+        assert_eq!(
+            subset.records,
+            vec![msa.records[2].clone(), msa.records[0].clone()]
+        );
+        assert_eq!(missing, vec!["id4".to_string()]);
+        assert_eq!(subset.get_column_annotation("cons").unwrap(), " * *");
+
+        msa.retain(|x| !x.sequence().contains('-'));
+        assert_eq!(msa.len(), 2);
+        assert!(!msa.contains("id2"));
+
+        assert_eq!(msa.remove("id1"), Some(Record::new("id1", "ACGT")));
+        assert_eq!(msa.remove("id1"), None);
+        assert_eq!(msa.len(), 1);
+        assert_eq!(msa.remove_record("id2"), None);
+        assert_eq!(msa.remove_record("id3"), Some(Record::new("id3", "TCGT")));
+        assert!(msa.is_empty());
+    }
+
+    #[test]
+    fn msa_slice() {
+        let file = std::fs::File::open("tests/clustalw.aln").unwrap();
+        let msa = crate::reader::Reader::new(std::io::BufReader::new(file))
+            .read_clustal()
+            .unwrap();
+
+        let sliced = msa.slice(0..50);
+        assert_eq!(sliced.len(), 2);
+        assert_eq!(sliced.col_len(), 50);
+        assert_eq!(sliced.get_column_annotation("cons").unwrap().len(), 50);
+        assert_eq!(sliced.get_annotation("version").unwrap(), "1.81");
+        assert_eq!(&sliced.records[1].sequence()[..12], "---------MSP");
+
+        let empty = msa.slice(10..10);
+        assert_eq!(empty.len(), 2);
+        assert_eq!(empty.col_len(), 0);
+        assert_eq!(empty.records[0].id(), msa.records[0].id());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn msa_slice_out_of_range() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        msa.slice(2..5);
+    }
+
+    #[test]
+    fn msa_unique_columns() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACAT");
+        msa.push_record("id2", "AGAT");
+        msa.add_column_annotation("cons", "*.**");
+
+        let (unique, mapping) = msa.unique_columns();
+        assert_eq!(unique.col_len(), 3);
+        assert_eq!(mapping, vec![0, 1, 0, 2]);
+        assert_eq!(unique.records[0].sequence(), "ACT");
+        assert_eq!(unique.records[1].sequence(), "AGT");
+        assert_eq!(unique.get_column_annotation("cons").unwrap(), "*.*");
+
+        // Every original column can be rebuilt from its representative
+        for (record, original) in unique.records.iter().zip(&msa.records) {
+            let rebuilt: String = mapping
+                .iter()
+                .map(|&i| record.sequence().as_bytes()[i] as char)
+                .collect();
+            assert_eq!(rebuilt, original.sequence());
+        }
+    }
+
+    #[test]
+    fn msa_gap_statistics() {
+        let mut msa = MSA::default();
+        assert!(msa.gap_fractions().is_empty());
+
+        msa.push_record("id1", "A-C.");
+        msa.push_record("id2", "A--G");
+        msa.push_record("id3", "AT-G");
+        msa.push_record("id4", "AT.G");
+        assert_eq!(msa.gap_fractions(), vec![0.0, 0.5, 0.75, 0.25]);
+        assert_eq!(msa.gap_count(2), Some(3));
+        assert_eq!(msa.gap_count(4), None);
+    }
+
+    #[test]
+    fn msa_remove_gap_columns() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "A-C-G");
+        msa.push_record("id2", "A.T-G");
+        msa.push_record("id3", "A-TAG");
+        msa.add_column_annotation("cons", "* . *");
+
+        let mut trimmed = msa.clone();
+        trimmed.remove_gap_columns();
+        assert_eq!(trimmed.col_len(), 4);
+        assert_eq!(trimmed.records[2].sequence(), "ATAG");
+        assert_eq!(trimmed.get_column_annotation("cons").unwrap(), "*. *");
+
+        msa.remove_columns_above_gap_fraction(0.5);
+        assert_eq!(msa.col_len(), 3);
+        assert!(msa.records.iter().all(|x| x.len() == 3));
+        assert_eq!(msa.records[1].sequence(), "ATG");
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), "*.*");
+    }
+
+    #[test]
+    fn msa_get_record() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC");
+        msa.push_record("id2", "AG");
+        msa.records.push(Record::new("id1", "TT"));
+
+        assert_eq!(msa.get_record("id1").unwrap().sequence(), "AC");
+        assert!(msa.get_record("id3").is_none());
+
+        msa.get_record_mut("id2").unwrap().push_seq("-");
+        assert_eq!(msa.get_record("id2").unwrap().sequence(), "AG-");
+        assert!(msa.get_record_mut("id3").is_none());
+    }
+
+    #[test]
+    fn msa_iter() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        msa.push_record("id2", "AC-T");
+
+        let mut ids = Vec::new();
+        for record in &msa {
+            ids.push(record.id().to_string());
+        }
+        assert_eq!(ids, vec!["id1", "id2"]);
+        assert_eq!(msa.iter().filter(|x| x.sequence().contains('-')).count(), 1);
+
+        for record in &mut msa {
+            record.push_annotation("checked", "yes");
+        }
+        for record in msa.iter_mut() {
+            record.push_seq("A");
+        }
+        let records: Vec<Record> = msa.into_iter().collect();
+        assert_eq!(records[1].sequence(), "AC-TA");
+        assert_eq!(records[1].get_annotation("checked").unwrap(), "yes");
+    }
+
+    #[test]
+    fn msa_index() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        msa.push_record("id2", "AC-T");
+
+        assert_eq!(msa[1].id(), "id2");
+        assert_eq!(msa.get(0).unwrap().sequence(), "ACGT");
+        assert!(msa.get(2).is_none());
+        assert_eq!(msa.get_by_id("id2"), Some(&msa[1]));
+        assert!(msa.get_by_id("id3").is_none());
+    }
+
+    #[test]
+    fn msa_validate() {
+        let mut msa = MSA::default();
+        assert!(msa.is_rectangular());
+        assert!(msa.validate().is_ok());
+
+        msa.push_record("id1", "ACGT");
+        msa.push_record("id2", "AC-T");
+        assert!(msa.validate().is_ok());
+
+        msa.push_record("id3", "ACGTA");
+        msa.push_record("id4", "A");
+        assert!(!msa.is_rectangular());
+        assert!(matches!(
+            msa.validate(),
+            Err(Error::UnequalLength { id, expected: 4, found: 5 }) if id == "id3"
+        ));
+    }
+
+    #[test]
+    fn msa_validate_annotations() {
+        let mut msa = MSA::default();
+        msa.try_push_record("id1", "AC").unwrap();
+        msa.try_push_record("id1", "GT").unwrap();
+        msa.try_push_record("id2", "AC-T").unwrap();
+        assert!(matches!(
+            msa.try_push_record("id2", "A"),
+            Err(Error::UnequalLength {
+                expected: 4,
+                found: 5,
+                ..
+            })
+        ));
+        assert!(msa.try_push_record("id3", "ACGTA").is_err());
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.records[1].sequence(), "AC-T");
+
+        msa.add_column_annotation("cons", "** ");
+        assert!(matches!(
+            msa.validate(),
+            Err(Error::UnequalAnnotationLength { name, expected: 4, found: 3 }) if name == "cons"
+        ));
+        msa.add_column_annotation("cons", "*");
+        assert!(msa.validate().is_ok());
+    }
+
+    #[test]
+    fn msa_concat() {
+        let mut gene1 = MSA::default();
+        gene1.push_record("sp1", "ACGT");
+        gene1.push_record("sp2", "AC-T");
+        gene1.add_column_annotation("cons", "** *");
+        gene1.add_annotation("gene".to_string(), "gene1".to_string());
+
+        let mut gene2 = MSA::default();
+        gene2.push_record("sp3", "MKV");
+        gene2.push_record("sp1", "MRV");
+        gene2.push_record_annotation("sp3", "SS", "HHH");
+        gene2.add_column_annotation("SS_cons", "HH.");
+        gene2.add_annotation("gene".to_string(), "gene2".to_string());
+
+        gene1.concat(&gene2);
+        assert_eq!(gene1.col_len(), 7);
+        assert!(gene1.validate().is_ok());
+        assert_eq!(gene1.records[0].sequence(), "ACGTMRV");
+        assert_eq!(gene1.records[1].sequence(), "AC-T---");
+        assert_eq!(gene1.records[2].sequence(), "----MKV");
+        assert_eq!(gene1.get_record_annotation("sp3", "SS").unwrap(), "HHH");
+        assert_eq!(gene1.get_column_annotation("cons").unwrap(), "** *   ");
+        assert_eq!(gene1.get_column_annotation("SS_cons").unwrap(), "    HH.");
+        assert_eq!(gene1.get_annotation("gene").unwrap(), "gene1");
+
+        let mut empty = MSA::default();
+        empty.concat(&gene2);
+        assert_eq!(empty.records, gene2.records);
+    }
+
+    #[test]
+    fn msa_join_extend() {
+        let mut gene1 = MSA::default();
+        gene1.push_record("sp1", "ACGT");
+        gene1.push_record("sp2", "AC-T");
+        gene1.add_column_annotation("cons", "** *");
+        let mut gene2 = MSA::default();
+        gene2.push_record("sp2", "MK");
+        gene2.push_record("sp1", "MR");
+        gene2.add_column_annotation("cons", ": ");
+
+        let joined = gene1.join(&gene2, false).unwrap();
+        assert_eq!(joined.records[0].sequence(), "ACGTMR");
+        assert_eq!(joined.records[1].sequence(), "AC-TMK");
+        assert_eq!(joined.get_column_annotation("cons").unwrap(), "** *: ");
+
+        gene2.push_record("sp3", "MV");
+        assert!(matches!(
+            gene1.join(&gene2, false),
+            Err(Error::RecordNotFound { id }) if id == "sp3"
+        ));
+        let filled = gene1.join(&gene2, true).unwrap();
+        assert_eq!(filled.records[2].sequence(), "----MV");
+        assert_eq!(gene1.col_len(), 4);
+
+        let mut stacked = gene1.clone();
+        stacked.add_annotation("source".to_string(), "left".to_string());
+        let mut other = MSA::default();
+        other.push_record("sp4", "TTTT");
+        other.add_annotation("source".to_string(), "right".to_string());
+        other.add_column_annotation("RF", "xxxx");
+        stacked.extend(other).unwrap();
+        assert_eq!(stacked.len(), 3);
+        assert_eq!(stacked.get_annotation("source").unwrap(), "left");
+        assert_eq!(stacked.get_column_annotation("RF").unwrap(), "xxxx");
+        assert_eq!(stacked.get_column_annotation("cons").unwrap(), "** *");
+        assert!(matches!(
+            stacked.extend(gene2),
+            Err(Error::UnequalLength {
+                expected: 4,
+                found: 2,
+                ..
+            })
+        ));
+        assert_eq!(stacked.len(), 3);
+    }
+
+    #[test]
+    fn msa_normalize_gaps() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "aC.g~");
+        msa.push_record("id2", "A-*gT");
+        msa.add_column_annotation("SS_cons", "<.~->");
+        msa.normalize_gaps('-');
+        msa.to_uppercase();
+        assert_eq!(msa.get(0).unwrap().sequence(), "AC-G-");
+        msa.normalize_gaps_with(&GapSet::from(['-', '*']), '.');
+        msa.to_lowercase();
+        assert_eq!(msa.get(0).unwrap().sequence(), "ac.g.");
+        assert_eq!(msa.get(1).unwrap().sequence(), "a..gt");
+        assert_eq!(msa.get_column_annotation("SS_cons").unwrap(), "<.~->");
+    }
+
+    #[test]
+    fn msa_translate() {
+        let table = CodonTable::standard();
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ATGAAA---");
+        msa.push_record("id2", "atgAAGtaa");
+        msa.push_record_annotation("id1", "SS", "HHHHHHHHH");
+        msa.add_annotation("program".to_string(), "MUSCLE".to_string());
+        msa.add_column_annotation("RF", "xxx...xxx");
+
+        let protein = msa.translate(&table).unwrap();
+        assert_eq!(protein.col_len(), 3);
+        assert_eq!(protein.get(0).unwrap().sequence(), "MK-");
+        assert_eq!(protein.get(1).unwrap().sequence(), "MK*");
+        assert_eq!(protein.get_record_annotation("id1", "SS"), None);
+        assert_eq!(protein.get_annotation("program").unwrap(), "MUSCLE");
+        assert_eq!(protein.get_column_annotation("RF").unwrap(), "x.x");
+
+        msa.push_record("id3", "ATG-AAAAA");
+        assert!(matches!(
+            msa.translate(&table),
+            Err(Error::BrokenReadingFrame { id, codon: 1, .. }) if id == "id3"
+        ));
+
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ATGA");
+        assert!(matches!(
+            msa.translate(&table),
+            Err(Error::BrokenReadingFrame { codon: 1, .. })
+        ));
+
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ATG~~~");
+        msa.set_gaps(GapSet::new(&['~']));
+        let protein = msa.translate(&table).unwrap();
+        assert_eq!(protein.get(0).unwrap().sequence(), "M-");
+    }
+
+    #[test]
+    fn msa_backtranslate() {
+        let mut protein = MSA::default();
+        protein.push_record("id1", "MK-W");
+        protein.push_record("id2", "M-RW");
+        protein.add_column_annotation("RF", "x.xx");
+        let cds = vec![
+            Record::new("id2", "ATG-CGCTGG"),
+            Record::new("id1", "atgaaatggtag"),
+        ];
+
+        let codons = protein.backtranslate(&cds).unwrap();
+        assert_eq!(codons.get(0).unwrap().sequence(), "atgaaa---tgg");
+        assert_eq!(codons.get(1).unwrap().sequence(), "ATG---CGCTGG");
+        assert_eq!(codons.get_column_annotation("RF").unwrap(), "xxx...xxxxxx");
+        assert_eq!(
+            codons.translate(&CodonTable::standard()).unwrap().get(1),
+            protein.get(1)
+        );
+
+        let short = vec![
+            Record::new("id1", "ATGAAATG"),
+            Record::new("id2", "ATGCGCTGG"),
+        ];
+        assert!(matches!(
+            protein.backtranslate(&short),
+            Err(Error::IncompatibleRecord { id, .. }) if id == "id1"
+        ));
+        assert!(matches!(
+            protein.backtranslate(&cds[..1]),
+            Err(Error::RecordNotFound { id }) if id == "id1"
+        ));
+    }
+
+    #[test]
+    fn msa_check_reading_frame() {
+        let table = CodonTable::standard();
+        let mut msa = MSA::default();
+        msa.push_record("ref", "ATGAA-AGGCTAG");
+        msa.push_record("stop", "ATGTGA-GGCTAG");
+        msa.push_record("short", "ATGAA--GGC-AG");
+        assert!(msa.check_reading_frame("ref", &table).is_ok());
+        assert!(matches!(
+            msa.check_reading_frame("stop", &table),
+            Err(Error::BrokenReadingFrame { codon: 1, .. })
+        ));
+        assert!(matches!(
+            msa.check_reading_frame("short", &table),
+            Err(Error::BrokenReadingFrame { codon: 3, .. })
+        ));
+        assert!(matches!(
+            msa.check_reading_frame("absent", &table),
+            Err(Error::RecordNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn msa_profile() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACa");
+        msa.push_record("id2", "AG.");
+        msa.push_record("id3", "TG-");
+        let profile = msa.profile();
+        assert_eq!(profile.len(), 3);
+        assert_eq!(profile[0], HashMap::from([('A', 2), ('T', 1)]));
+        assert_eq!(profile[1], HashMap::from([('C', 1), ('G', 2)]));
+        assert_eq!(profile[2], HashMap::from([('a', 1), ('.', 1), ('-', 1)]));
+        assert!(MSA::default().profile().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn msa_serde_round_trip() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC-GT");
+        msa.push_record("id2", "ACGGT");
+        msa.push_record_annotation("id1", "SS", "<<.>>");
+        msa.add_annotation("program".to_string(), "CLUSTAL".to_string());
+        msa.add_column_annotation("cons", "** **");
+
+        let json = serde_json::to_string(&msa).unwrap();
+        assert_eq!(serde_json::from_str::<MSA>(&json).unwrap(), msa);
+        let bytes = bincode::serialize(&msa).unwrap();
+        assert_eq!(bincode::deserialize::<MSA>(&bytes).unwrap(), msa);
+
+        let record = msa.get(0).unwrap();
+        let json = serde_json::to_string(record).unwrap();
+        assert_eq!(&serde_json::from_str::<Record>(&json).unwrap(), record);
+
+        let ragged = serde_json::json!({
+            "records": [
+                {"id": "id1", "sequence": "ACGT", "annotation": {}},
+                {"id": "id2", "sequence": "AC", "annotation": {}}
+            ],
+            "annotations": {},
+            "column_annotations": {}
+        });
+        let err = serde_json::from_value::<MSA>(ragged).unwrap_err();
+        assert!(err.to_string().contains("id2"));
+    }
+
+    #[test]
+    fn msa_valdar_conservation() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "IWK");
+        msa.push_record("id2", "VDK");
+        msa.push_record("id3", "LGK");
+        msa.push_record("id4", "IP-");
+        let weights: Vec<(String, f64)> = ["id1", "id2", "id3", "id4"]
+            .iter()
+            .map(|id| (id.to_string(), 0.25))
+            .collect();
+        let scores = msa.valdar_conservation(&ScoringMatrix::blosum62(-4), &weights);
+        assert_eq!(scores.len(), 3);
+        assert!(scores[0] > 0.4);
+        assert!(scores[1] < 0.15);
+        assert!(scores[0] > scores[1]);
+        // Gaps score 0: 3 of the 6 pairs are K-K
+        assert!((scores[2] - 0.5 * 9.0 / 15.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn msa_reorder() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let mut msa = Reader::new(open("tests/clustalw.aln"))
+            .read_clustal()
+            .unwrap();
+        let original = msa.clone();
+        let first = original.get(0).unwrap().id().to_string();
+        let second = original.get(1).unwrap().id().to_string();
+
+        msa.reorder(&[&second, &first], false).unwrap();
+        assert_eq!(msa.get(0).unwrap().id(), second);
+        assert_eq!(msa.get(1), original.get(0));
+        assert_eq!(
+            msa.get_column_annotation("cons"),
+            original.get_column_annotation("cons")
+        );
+
+        let err = msa.reorder(&[&first, "absent", &first], false).unwrap_err();
+        match &err {
+            Error::InvalidOrder {
+                missing,
+                duplicated,
+                unlisted,
+            } => {
+                assert_eq!(missing, &["absent"]);
+                assert_eq!(duplicated, &[first.as_str()]);
+                assert_eq!(unlisted, &[second.as_str()]);
+            }
+            _ => panic!("unexpected error {}", err),
+        }
+        assert_eq!(msa.get(0).unwrap().id(), second);
+
+        msa.reorder(&[&first], true).unwrap();
+        assert_eq!(msa, original);
+    }
+
+    #[test]
+    fn msa_sort_by_id() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let sorted = Reader::new(open("tests/sorted.fa")).read_fasta().unwrap();
+        let mut msa = sorted.clone();
+        msa.sort_by(|a, b| b.sequence().cmp(a.sequence()));
+        assert_ne!(msa, sorted);
+        msa.sort_by_id();
+        assert_eq!(msa, sorted);
+
+        let mut msa = MSA::new(
+            vec![
+                Record::new("b", "A"),
+                Record::new("a", "C"),
+                Record::new("b", "G"),
+                Record::new("a", "T"),
+            ],
+            HashMap::new(),
+            HashMap::new(),
+        );
+        msa.sort_by_id();
+        let sequences: Vec<&str> = msa.iter().map(|x| x.sequence()).collect();
+        assert_eq!(sequences, vec!["C", "T", "A", "G"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn msa_serde_parsed_round_trip() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let msa = Reader::new(open("tests/pfam.sto"))
+            .read_stockholm()
+            .unwrap();
+        let json = serde_json::to_string_pretty(&msa).unwrap();
+        let parsed: MSA = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, msa);
+        assert_eq!(
+            parsed.get_record_annotation("DPS_ECOLI/5-150", "SS"),
+            msa.get_record_annotation("DPS_ECOLI/5-150", "SS")
+        );
+    }
+
+    #[test]
+    fn msa_compute_conservation() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        for path in ["tests/clustalw.aln", "tests/protein.aln"] {
+            let parsed = Reader::new(open(path)).read_clustal().unwrap();
+            let mut msa = MSA::new(parsed.records().to_vec(), HashMap::new(), HashMap::new());
+            msa.compute_conservation();
+            assert_eq!(
+                msa.get_column_annotation("cons"),
+                parsed.get_column_annotation("cons"),
+                "{}",
+                path
+            );
+        }
+
+        // An existing track is replaced, not extended
+        let mut parsed = Reader::new(open("tests/clustalw.aln"))
+            .read_clustal()
+            .unwrap();
+        let expected = parsed.get_column_annotation("cons").unwrap().clone();
+        parsed.compute_conservation();
+        parsed.compute_conservation();
+        assert_eq!(parsed.get_column_annotation("cons").unwrap(), &expected);
+        assert!(parsed.validate().is_ok());
+
+        let mut msa = MSA::default();
+        msa.compute_conservation();
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), "");
+    }
+
+    #[test]
+    fn msa_stats() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGTA");
+        msa.push_record("id2", "aCG-A");
+        msa.push_record("id3", "TCG--");
+        let stats = msa.stats();
+        assert_eq!(stats.num_sequences(), 3);
+        assert_eq!(stats.alignment_length(), 5);
+        assert_eq!(stats.gap_fraction(), 0.2);
+        assert_eq!(stats.record_gap_fractions()[2], ("id3".to_string(), 0.4));
+        assert_eq!(stats.conserved_columns(), 2);
+        assert!((stats.mean_identity().unwrap() - 25.0 / 36.0).abs() < 1e-12);
+        assert_eq!(
+            msa.stats_sampled(Some(2)).mean_identity(),
+            msa.pairwise_identity("id1", "id2")
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Sequences: 3\nColumns: 5\nGap fraction: 0.2000\nConserved columns: 2\n\
+             Mean pairwise identity: 0.6944\nGap fraction per sequence:\n\
+             \x20 id1\t0.0000\n  id2\t0.2000\n  id3\t0.4000\n"
+        );
+
+        let empty = MSA::default().stats();
+        assert_eq!(empty.gap_fraction(), 0.0);
+        assert_eq!(empty.conserved_columns(), 0);
+        assert_eq!(empty.mean_identity(), None);
+
+        let mut single = MSA::default();
+        single.push_record("id1", "A-");
+        let stats = single.stats_sampled(None);
+        assert_eq!(stats.gap_fraction(), 0.5);
+        assert_eq!(stats.conserved_columns(), 1);
+        assert_eq!(stats.mean_identity(), None);
+        assert_eq!(single.stats_sampled(Some(0)).mean_identity(), None);
+    }
+
+    #[test]
+    fn msa_guess_alphabet_sampled() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", &format!("{}{}", "AC-GT".repeat(100), "MKLVW"));
+        msa.push_record("id2", &format!("{}{}", "-TGCA".repeat(100), "-----"));
+        assert_eq!(msa.guess_alphabet(), Alphabet::Protein);
+        assert_eq!(msa.guess_alphabet_sampled(100), Alphabet::Dna);
+        assert_eq!(msa.guess_alphabet_sampled(0), Alphabet::Unknown);
+        assert_eq!(MSA::default().guess_alphabet_sampled(10), Alphabet::Unknown);
+    }
+
+    #[test]
+    fn msa_column_tracks() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        msa.push_record("id2", "AC-T");
+        msa.set_column_track("cons", ColumnTrack::Chars("** *".to_string()))
+            .unwrap();
+        msa.set_column_track("pp", ColumnTrack::Scores(vec![0.9, 0.8, 0.1, 0.7]))
+            .unwrap();
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), "** *");
+        assert_eq!(msa.get_column_annotation("pp"), None);
+        assert!(matches!(
+            msa.set_column_track("pp", ColumnTrack::Scores(vec![1.0])),
+            Err(Error::UnequalAnnotationLength {
+                expected: 4,
+                found: 1,
+                ..
+            })
+        ));
+
+        // Columns are bytes, so non-ASCII characters cannot line up
+        let mut short = MSA::default();
+        short.push_record("id1", "AC");
+        assert!(matches!(
+            short.set_column_track("ss", ColumnTrack::Chars("é*".to_string())),
+            Err(Error::NonAsciiAnnotation {
+                column: 0,
+                value: 'é',
+                ..
+            })
+        ));
+        assert!(matches!(
+            short.set_column_track("ss", ColumnTrack::Chars("é".to_string())),
+            Err(Error::NonAsciiAnnotation { .. })
+        ));
+        assert_eq!(ColumnTrack::Chars("é*".to_string()).len(), 3);
+        assert_eq!(short.column_track("ss"), None);
+        assert!(short.validate().is_ok());
+
+        let sliced = msa.slice(1..3);
+        assert_eq!(
+            sliced.column_track("pp"),
+            Some(ColumnTrack::Scores(vec![0.8, 0.1]))
+        );
+        let reversed = msa.reverse_complement().unwrap();
+        assert_eq!(
+            reversed.column_track("pp"),
+            Some(ColumnTrack::Scores(vec![0.7, 0.1, 0.8, 0.9]))
+        );
+        let (trimmed, _) = msa.trim_gap_columns(0.0);
+        assert_eq!(
+            trimmed.column_track("pp"),
+            Some(ColumnTrack::Scores(vec![0.9, 0.8, 0.7]))
+        );
+        assert_eq!(trimmed.get_column_annotation("cons").unwrap(), "***");
+
+        let mut joined = sliced.clone();
+        let mut other = MSA::default();
+        other.push_record("id1", "T");
+        other.push_record("id2", "T");
+        joined.concat(&other);
+        match joined.column_track("pp") {
+            Some(ColumnTrack::Scores(values)) => {
+                assert_eq!(values[..2], [0.8, 0.1]);
+                assert!(values[2].is_nan());
+            }
+            track => panic!("unexpected track {:?}", track),
+        }
+        joined.validate().unwrap();
+
+        // Replacing a track of the other kind
+        msa.set_column_track("cons", ColumnTrack::Scores(vec![1.0; 4]))
+            .unwrap();
+        assert_eq!(msa.get_column_annotation("cons"), None);
+    }
+
+    #[test]
+    fn msa_window_consensus() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGTACGTAC");
+        msa.push_record("id2", "ACGTTCGTAG");
+        msa.push_record("id3", "ACCTTCG-AG");
+        assert_eq!(
+            msa.window_consensus(4, 2),
+            vec![
+                (0, "ACGT".to_string()),
+                (2, "GTTC".to_string()),
+                (4, "TCGT".to_string()),
+                (6, "GTAG".to_string()),
+                (8, "AG".to_string()),
+            ]
+        );
+        assert!(msa.window_consensus(0, 2).is_empty());
+        assert!(MSA::default().window_consensus(4, 2).is_empty());
+    }
+
+    #[test]
+    fn msa_display_multibyte() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", &format!("{}é{}", "A".repeat(29), "C".repeat(5)));
+        msa.push_record("id2", &"é".repeat(30));
+        assert_eq!(
+            msa.to_string(),
+            format!(
+                "Alignment with 2 rows and 36 columns\nid1\t{}é...\nid2\t{}\n",
+                "A".repeat(29),
+                "é".repeat(30)
+            )
+        );
+    }
+
+    #[test]
+    fn msa_get_column() {
+        let mut msa = MSA::default();
+        assert_eq!(msa.get_column(0), None);
+
+        msa.push_record("id1", "AéC");
+        msa.push_record("id2", "A-G-");
+        assert_eq!(msa.get_column(0), Some("AA".to_string()));
+        assert_eq!(msa.get_column(3), Some("C-".to_string()));
+        assert_eq!(msa.get_column(1).map(|x| x.chars().count()), Some(2));
+        assert_eq!(msa.get_column(4), None);
+
+        msa.push_record("id3", "A");
+        assert_eq!(msa.get_column(3), Some("C--".to_string()));
+        assert_eq!(
+            msa.get_column(3).unwrap().len(),
+            msa.column(3).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn msa_trim_gap_columns() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC-GT.");
+        msa.push_record("id2", "A--GTA");
+        msa.push_record("id3", "ACTG-A");
+        msa.push_record("id4", "ACTGTA");
+        msa.add_column_annotation("cons", "*.:**.");
+
+        let (trimmed, kept) = msa.trim_gap_columns(0.0);
+        assert_eq!(kept, vec![0, 3]);
+        assert_eq!(trimmed.records[2].sequence(), "AG");
+        assert_eq!(trimmed.get_column_annotation("cons").unwrap(), "**");
+
+        let (trimmed, kept) = msa.trim_gap_columns(0.25);
+        assert_eq!(kept, vec![0, 1, 3, 4, 5]);
+        assert_eq!(trimmed.get_column_annotation("cons").unwrap(), "*.**.");
+        assert_eq!(msa.col_len(), 6);
+
+        assert_eq!(
+            msa.remove_columns_above_gap_fraction(0.5),
+            (0..6).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn msa_annotation_iterators() {
+        let file = File::open("tests/pfam.sto").unwrap();
+        let msa = Reader::new(BufReader::new(file)).read_stockholm().unwrap();
+        let mut names: Vec<&String> = msa.annotations().map(|(k, _)| k).collect();
+        names.sort();
+        assert!(!names.is_empty());
+        for (name, value) in msa.annotations() {
+            assert_eq!(msa.get_annotation(name), Some(value));
+        }
+        for (name, value) in msa.column_annotations() {
+            assert_eq!(msa.get_column_annotation(name), Some(value));
+            assert_eq!(value.len(), msa.col_len());
+        }
+        assert_eq!(MSA::default().annotations().count(), 0);
+        assert_eq!(MSA::default().column_annotations().count(), 0);
+    }
+
+    #[test]
+    fn msa_gap_set() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "~AC-T*");
+        msa.push_record("id2", "~.C-TA");
+        msa.add_column_annotation("cons", " .:*.:");
+
+        // The default set leaves the behaviour unchanged
+        assert_eq!(GapSet::default(), GapSet::new(&['-', '.']));
+        assert_eq!(
+            msa.gap_fractions_with(&GapSet::default()),
+            msa.gap_fractions()
+        );
+
+        let gaps = GapSet::new(&['-', '~', '*']);
+        assert_eq!(
+            msa.gap_fractions_with(&gaps),
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.5]
+        );
+        let mut default = msa.clone();
+        default.remove_gap_columns();
+        assert_eq!(default.col_len(), 5);
+
+        msa.remove_gap_columns_with(&gaps);
+        assert_eq!(msa.get(0).unwrap().sequence(), "ACT*");
+        assert_eq!(msa.get(1).unwrap().sequence(), ".CTA");
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), ".:.:");
+        assert_eq!(msa.get(0).unwrap().ungap_with(&gaps).sequence(), "ACT");
+    }
+
+    #[test]
+    fn msa_set_gaps() {
+        let mut msa = MSA::default();
+        msa.push_record("a", "~~ACGT");
+        msa.push_record("b", "AAACGA");
+        assert_eq!(msa.gaps(), &GapSet::default());
+        assert_eq!(msa.pairwise_identity("a", "b"), Some(0.5));
+        assert!(msa.validate_alphabet(Alphabet::Dna).is_err());
+
+        msa.set_gaps(GapSet::from(['-', '.', '~']));
+        assert_eq!(msa.pairwise_identity("a", "b"), Some(0.75));
+        assert_eq!(msa.gap_fractions()[..2], [0.5, 0.5]);
+        assert!(msa.validate_alphabet(Alphabet::Dna).is_ok());
+        let trimmed = msa.trim_to_reference("a").unwrap();
+        assert_eq!(trimmed.get_record("b").unwrap().sequence(), "ACGA");
+        assert_eq!(trimmed.gaps(), msa.gaps());
+        assert_eq!(msa.slice(0..2).gaps(), msa.gaps());
+    }
+
+    #[test]
+    fn msa_trim_to_reference() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC-GT.");
+        msa.push_record("id2", "A--GTA");
+        msa.add_column_annotation("cons", "*.:**.");
+        msa.set_column_track(
+            "score",
+            ColumnTrack::Scores(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+        )
+        .unwrap();
+
+        let trimmed = msa.trim_to_reference("id2").unwrap();
+        assert_eq!(trimmed.get_record("id2").unwrap().sequence(), "AGTA");
+        assert_eq!(trimmed.get_record("id1").unwrap().sequence(), "AGT.");
+        assert_eq!(trimmed.get_column_annotation("cons").unwrap(), "***.");
+        assert_eq!(
+            trimmed.column_track("score"),
+            Some(ColumnTrack::Scores(vec![0.0, 3.0, 4.0, 5.0]))
+        );
+
+        let trimmed = msa.trim_to_reference("id1").unwrap();
+        assert_eq!(trimmed.col_len(), 4);
+        assert_eq!(trimmed.get_record("id2").unwrap().sequence(), "A-GT");
+        assert!(msa.trim_to_reference("id3").is_none());
+    }
+
+    #[test]
+    fn msa_columns() {
+        let mut msa = MSA::default();
+        assert_eq!(msa.columns().count(), 0);
+        assert_eq!(msa.column(0), None);
+
+        msa.push_record("id1", "ACG");
+        msa.push_record("id2", "A");
+        msa.push_record("id3", "TCGA");
+        assert_eq!(msa.column(1), Some(b"C-C".to_vec()));
+        assert_eq!(msa.column(3), None);
+        let columns: Vec<Vec<u8>> = msa.columns().collect();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns[0], b"AAT".to_vec());
+        assert_eq!(columns[2], b"G-G".to_vec());
+        assert_eq!(msa.to_columns(), columns);
+        assert!(MSA::default().to_columns().is_empty());
+    }
+
+    #[test]
+    fn msa_slice_columns() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGTAC");
+        msa.push_record("id2", "A-GTTC");
+        msa.add_column_annotation("cons", "* ** *");
+        msa.add_annotation("source".to_string(), "test".to_string());
+
+        let domain = msa.slice_columns(1..4);
+        assert_eq!(domain.records[1].sequence(), "-GT");
+        assert_eq!(domain.get_column_annotation("cons").unwrap(), " **");
+        assert_eq!(domain.get_annotation("source").unwrap(), "test");
+        assert_eq!(msa.col_len(), 6);
+
+        assert_eq!(msa.slice_columns(3..100).records[0].sequence(), "TAC");
+        assert_eq!(msa.slice_columns(8..100).col_len(), 0);
+        assert_eq!(msa.slice_columns(0..100), msa);
+    }
+
+    #[test]
+    fn msa_partition_by_reliability() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGTAC");
+        msa.push_record("id2", "A--TTC");
+        msa.add_column_annotation("cons", "*  * *");
+
+        let (kept, removed) = msa.partition_by_reliability(&[true, false, false, true]);
+        assert_eq!(kept.col_len() + removed.col_len(), msa.col_len());
+        assert_eq!(kept.records[1].sequence(), "AT");
+        assert_eq!(removed.records[1].sequence(), "--TC");
+        assert_eq!(kept.get_column_annotation("cons").unwrap(), "**");
+        assert_eq!(removed.get_column_annotation("cons").unwrap(), "   *");
+    }
+
+    #[test]
+    fn msa_reverse_complement() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAC-G");
+        msa.push_record("id2", "ATCCG");
+        msa.add_column_annotation("cons", "* * *");
+        msa.add_column_annotation("RF", "xx.xx");
+
+        let reversed = msa.reverse_complement().unwrap();
+        assert_eq!(reversed.records[0].sequence(), "C-GTT");
+        assert_eq!(reversed.records[1].sequence(), "CGGAT");
+        assert_eq!(reversed.get_column_annotation("RF").unwrap(), "xx.xx");
+        assert_eq!(reversed.reverse_complement().unwrap(), msa);
+
+        msa.push_record("id3", "MKL-E");
+        assert!(matches!(
+            msa.reverse_complement(),
+            Err(Error::InvalidResidue { id, column: 2, .. }) if id == "id3"
+        ));
+    }
+
+    #[test]
+    fn msa_preferred_gap_columns() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC--GT.A");
+        msa.push_record("id2", "A---GT-A");
+        msa.push_record("id3", "ACG-G--A");
+        msa.push_record("id4", "ACGTGT-A");
+        assert_eq!(
+            msa.gap_profile(),
+            vec![0.0, 0.25, 0.5, 0.75, 0.0, 0.25, 1.0, 0.0]
+        );
+        assert_eq!(msa.preferred_gap_columns(0.5), vec![2, 3, 6]);
+        assert_eq!(msa.preferred_gap_columns(0.8), vec![6]);
+        assert!(MSA::default().preferred_gap_columns(0.0).is_empty());
+    }
+
+    #[test]
+    fn msa_column_effective_n() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACG~");
+        msa.push_record("id2", "A-G~");
+        msa.push_record("id3", "A-.T");
+        let weights = vec![
+            ("id3".to_string(), 0.5),
+            ("id1".to_string(), 1.0),
+            ("id2".to_string(), 2.0),
+        ];
+        let effective = msa.column_effective_n(&weights, &GapSet::default());
+        assert_eq!(effective, vec![3.5, 1.0, 3.0, 3.5]);
+        assert!(effective[1] < effective[0]);
+        assert_eq!(
+            msa.column_effective_n(&weights, &GapSet::from(['~']))[3],
+            0.5
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no weight for record id2")]
+    fn msa_column_effective_n_missing_weight() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AC");
+        msa.push_record("id2", "A-");
+        msa.column_effective_n(&[("id1".to_string(), 1.0)], &GapSet::from(['-']));
+    }
+
+    #[test]
+    fn msa_to_uppercase() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "acgT");
+        msa.push_record("id2", "ac-t");
+        msa.add_column_annotation("RF", "xx.x");
+        msa.to_uppercase();
+        assert_eq!(msa.records[0].sequence(), "ACGT");
+        assert_eq!(msa.records[1].sequence(), "AC-T");
+        assert_eq!(msa.get_column_annotation("RF").unwrap(), "xx.x");
+    }
+
+    #[test]
+    fn msa_rethread() {
+        let mut template = MSA::default();
+        template.push_record("ref", "-ACG-T");
+
+        let mut target = MSA::default();
+        target.push_record("ref", "AC-GT");
+        target.push_record("other", "AC-GA");
+        target.add_column_annotation("cons", "** **");
+        target.rethread(&template, "ref").unwrap();
+        assert_eq!(target.records[0].sequence(), "-ACG-T");
+        assert_eq!(target.records[1].sequence(), "-ACG-A");
+        assert_eq!(target.get_column_annotation("cons").unwrap(), " *** *");
+
+        let mut target = MSA::default();
+        target.push_record("ref", "AC-GT");
+        target.push_record("other", "ACTGA");
+        let before = target.clone();
+        assert!(matches!(
+            target.rethread(&template, "ref"),
+            Err(Error::IncompatibleRecord { .. })
+        ));
+        assert_eq!(target, before);
+        assert!(matches!(
+            target.rethread(&template, "other"),
+            Err(Error::RecordNotFound { .. })
+        ));
+
+        // Records shorter than the shared one read as gaps past their end
+        let mut template = MSA::default();
+        template.push_record("ref", "AC");
+        let mut target = MSA::default();
+        target.push_record("ref", "A--C");
+        target.push_record("o", "A");
+        target.rethread(&template, "ref").unwrap();
+        assert_eq!(target.records[0].sequence(), "AC");
+        assert_eq!(target.records[1].sequence(), "A-");
+    }
+
+    #[test]
+    fn msa_column_mismatch_counts() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AA");
+        msa.push_record("id2", "AA");
+        msa.push_record("id3", "AG");
+        msa.push_record("id4", "AG");
+        msa.push_record("id5", "AG");
+        assert_eq!(msa.column_mismatch_counts(true), vec![0, 2 * 3]);
+    }
+
+    #[test]
+    fn msa_nucleotide_diversity() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAAAA");
+        assert_eq!(msa.nucleotide_diversity(true), 0.0);
+
+        msa.push_record("id2", "AAAAT");
+        msa.push_record("id3", "AAGAT");
+        msa.push_record("id4", "AAGAA");
+        // 8 differing pairs over 6 pairs and 5 sites
+        let pi = msa.nucleotide_diversity(true);
+        assert!((pi - 8.0 / 6.0 / 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn msa_segregating_sites_and_theta() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAAAA");
+        msa.push_record("id2", "AAAAT");
+        msa.push_record("id3", "AAGAT");
+        msa.push_record("id4", "AA-AA");
+        assert_eq!(msa.segregating_sites(true), 2);
+        assert_eq!(msa.segregating_sites(false), 2);
+
+        msa.push_record("id5", "A-AAA");
+        assert_eq!(msa.segregating_sites(true), 2);
+        assert_eq!(msa.segregating_sites(false), 3);
+
+        // a_5 = 1 + 1/2 + 1/3 + 1/4
+        let theta = msa.wattersons_theta(true);
+        assert!((theta - 2.0 / (25.0 / 12.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn msa_tajimas_d() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ATAAAAAAAA");
+        msa.push_record("id2", "AAAAAAAAAA");
+        msa.push_record("id3", "AAAAGAAAAA");
+        assert_eq!(msa.tajimas_d(true), None);
+
+        msa.push_record("id4", "AATAAAAAAC");
+        msa.push_record("id5", "AAAAAAAAAC");
+        msa.push_record("id6", "AAAAAACAAC");
+        // S = 5, k = 29 / 15, equation 38 of Tajima (1989)
+        let d = msa.tajimas_d(true).unwrap();
+        assert!((d - -0.6554316973713603).abs() < 1e-9);
+
+        let mut conserved = MSA::default();
+        for id in ["id1", "id2", "id3", "id4"] {
+            conserved.push_record(id, "ACGT");
+        }
+        assert_eq!(conserved.tajimas_d(true), None);
+    }
+
+    #[test]
+    fn msa_consensus() {
+        let mut msa = MSA::default();
+        assert_eq!(msa.consensus(0.5), "");
+
+        msa.push_record("id1", "AC-TG");
+        msa.push_record("id2", "AC-TC");
+        msa.push_record("id3", "AG-AT");
+        msa.push_record("id4", "A--AA");
+        assert_eq!(msa.consensus(0.5), "ACXAX");
+        assert_eq!(msa.consensus_with(0.7, '?'), "A????");
+        assert_eq!(msa.consensus_with(0.0, 'N'), "ACNAA");
+    }
+
+    #[test]
+    fn msa_mask_columns() {
+        let file = File::open("tests/clustalw.aln").unwrap();
+        let msa = Reader::new(BufReader::new(file)).read_clustal().unwrap();
+        let cons = msa.get_column_annotation("cons").unwrap();
+        assert_eq!(&cons[8..18], "  * *: :: ");
+
+        let mut masked = msa.clone();
+        masked.mask_range(10..16, 'X').unwrap();
+        assert_eq!(masked.col_len(), msa.col_len());
+        assert_eq!(&masked.get(0).unwrap().sequence()[8..18], "KGXXXXXXQR");
+        assert_eq!(&masked.get(1).unwrap().sequence()[8..18], "-MXXXXXXKA");
+        let masked_cons = masked.get_column_annotation("cons").unwrap();
+        assert_eq!(&masked_cons[8..18], "        : ");
+        assert_eq!(&masked_cons[16..], &cons[16..]);
+
+        let copy = msa.masked_copy(&[0, 9], 'X').unwrap();
+        assert_eq!(&copy.get(0).unwrap().sequence()[..10], "XENSDSNDKX");
+        assert_eq!(&copy.get(1).unwrap().sequence()[..10], "---------X");
+        assert_eq!(
+            &copy.get_column_annotation("cons").unwrap()[..10],
+            "          "
+        );
+
+        let len = msa.col_len();
+        let mut unchanged = msa.clone();
+        assert!(matches!(
+            unchanged.mask_columns(&[0, len], 'X'),
+            Err(Error::ColumnOutOfRange { column, len: l }) if column == len && l == len
+        ));
+        assert_eq!(unchanged, msa);
+
+        unchanged.mask_columns_with(&[1], 'N', '#').unwrap();
+        assert_eq!(&unchanged.get_column_annotation("cons").unwrap()[..2], " #");
+    }
+
+    #[test]
+    fn msa_mask_low_complexity() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAAAAAACGTACGT");
+        msa.push_record("id2", "AAA-AAACGTTCGT");
+
+        let masked = msa.mask_low_complexity(4, 2, 'N');
+        assert_eq!(masked.records[0].sequence(), "NNNNNNNCGTACGT");
+        assert_eq!(masked.records[1].sequence(), "NNN-NNNCGTTCGT");
+        assert_eq!(msa.mask_low_complexity(4, 1, 'N'), msa);
+    }
+
+    #[test]
+    fn msa_consensus_options() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "-aAC");
+        msa.push_record("id2", "-aGC");
+        msa.push_record("id3", "-AG-");
+        msa.push_record("id4", "-CA-");
+
+        // All-gap column and tie column (A/G)
+        assert_eq!(msa.consensus(0.5), "XAAC");
+        let ties = ConsensusOptions::new(0.5).ties_to_placeholder(true);
+        assert_eq!(msa.consensus_with_options(&ties), "XAXC");
+
+        // Gaps in the denominator drop the last column below 0.6
+        let gaps = ConsensusOptions::new(0.6).count_gaps(true).placeholder('N');
+        assert_eq!(msa.consensus_with_options(&gaps), "NANN");
+
+        msa.annotate_consensus(&ConsensusOptions::default());
+        msa.annotate_consensus(&ConsensusOptions::default());
+        assert_eq!(msa.get_column_annotation("consensus").unwrap(), "XAAC");
+    }
+
+    #[test]
+    fn msa_motif_coverage() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "MCPHCGK");
+        msa.push_record("id2", "MCAHCGR");
+        msa.push_record("id3", "MSPHCGK");
+        msa.push_record("id4", "LCPHCAK");
+
+        let coverage = msa.motif_coverage(&[1, 3, 4]);
+        assert_eq!(coverage[0], ("id1".to_string(), 1.0));
+        assert_eq!(coverage[1].1, 1.0);
+        assert!((coverage[2].1 - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(msa.motif_coverage(&[])[0].1, 0.0);
+    }
+
+    #[test]
+    fn msa_gap_aware_consensus() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AA");
+        msa.push_record("id2", "AA");
+        msa.push_record("id3", "C-");
+        msa.push_record("id4", "-.");
+        msa.push_record("id5", "--");
+        assert_eq!(msa.gap_aware_consensus(), "A-");
+
+        let mut msa = MSA::default();
+        msa.push_record("id1", "~C");
+        msa.push_record("id2", "AC");
+        msa.set_gaps(GapSet::new(&['~']));
+        assert_eq!(msa.gap_aware_consensus(), "AC");
+    }
+
+    #[test]
+    fn msa_column_entropy() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAA-");
+        msa.push_record("id2", "ACC-");
+        msa.push_record("id3", "AGG.");
+        msa.push_record("id4", "ATT-");
+
+        let entropy = msa.column_entropy(true);
+        assert_eq!(entropy[0], 0.0);
+        assert_eq!(entropy[1], 2.0);
+        assert_eq!(entropy[3], 0.0);
+        assert_eq!(msa.column_entropy(false)[3], 0.0);
+    }
+
+    #[test]
+    fn msa_column_tcs() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "MKVLAW");
+        msa.push_record("id2", "MKVLAW");
+        msa.push_record("id3", "MKVAWL");
+
+        // id3 is best aligned to the others with a gap before its A
+        let tcs = msa.column_tcs(&ScoringMatrix::new(1, -1, -2));
+        assert_eq!(tcs[0], 1.0);
+        assert!(tcs[3] < tcs[0]);
+        assert!((tcs[3] - 1.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn msa_identity_matrix() {
+        let mut msa = MSA::default();
+        assert!(msa.identity_matrix().is_empty());
+
+        msa.push_record("id1", "ACGT-A");
+        assert_eq!(msa.identity_matrix(), vec![vec![1.0]]);
+
+        msa.push_record("id2", "ACCT-A");
+        msa.push_record("id3", "----TT");
+        let matrix = msa.identity_matrix();
+        assert_eq!(matrix[0][1], 0.8);
+        assert_eq!(matrix[1][0], 0.8);
+        assert_eq!(matrix[0][2], 0.0);
+        assert_eq!(matrix[2][2], 1.0);
+    }
+
+    #[test]
+    fn msa_identity_gap_policy() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGTAC-");
+        msa.push_record("id2", "ACGAA--");
+        msa.push_record("id3", "A-GTACT");
+        msa.push_record("id4", "ACGTAC-");
+
+        let matrix = msa.identity_matrix_with(GapPolicy::Mismatch);
+        assert_eq!(matrix[0][1], 4.0 / 6.0);
+        assert_eq!(matrix[0][2], 5.0 / 7.0);
+        assert_eq!(matrix[1][2], 3.0 / 7.0);
+        assert_eq!(matrix[0][3], 1.0);
+        assert_eq!(msa.pairwise_identity("id1", "id3"), Some(1.0));
+        assert_eq!(msa.pairwise_identity("id2", "id3"), Some(0.75));
+        assert_eq!(msa.pairwise_identity("id1", "id5"), None);
+
+        assert_eq!(msa.dedupe(0.9), vec!["id3".to_string(), "id4".to_string()]);
+        assert_eq!(msa.len(), 2);
+    }
+
+    #[test]
+    fn msa_identity_to_query() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "MKV-LITGAG");
+        msa.push_record("id2", "MRVALLSG--");
+        msa.push_record("id3", "----------");
+        let scoring = ScoringMatrix::blosum62(-4);
+
+        let identities = msa.identity_to_query("mkvLITGAG", &scoring, true);
+        assert_eq!(identities[0], ("id1".to_string(), 1.0));
+        assert!(identities[1].1 < 0.7);
+        assert_eq!(identities[2], ("id3".to_string(), 0.0));
+
+        let identities = msa.identity_to_query("MKVLITGA-", &scoring, false);
+        assert_eq!(identities[0].1, 8.0 / 9.0);
+    }
+
+    #[test]
+    fn msa_occupancy_consensus() {
+        let mut msa = MSA::default();
+        msa.push_record("id0", "AKG");
+        for i in 1..10 {
+            msa.push_record(&format!("id{}", i), "AR-");
+        }
+        assert_eq!(msa.occupancy_consensus(0.5, '_'), "AR_");
+        assert_eq!(msa.occupancy_consensus(0.1, '_'), "ARG");
+        assert_eq!(MSA::default().occupancy_consensus(0.5, '_'), "");
+    }
+
+    #[test]
+    fn msa_coverage_adjusted_distance() {
+        let mut msa = MSA::default();
+        msa.push_record("full1", "ACGTACGTAC");
+        msa.push_record("full2", "ACGTACGTAA");
+        msa.push_record("frag1", "ACGTAC----");
+        msa.push_record("frag2", "-----CGTAC");
+        msa.push_record("gaps", "----------");
+
+        let full = msa.coverage_adjusted_distance("full1", "full2").unwrap();
+        let partial = msa.coverage_adjusted_distance("frag1", "frag2").unwrap();
+        assert!((full - 0.1).abs() < 1e-12);
+        assert!((partial - 0.8).abs() < 1e-12);
+        assert!(partial > full);
+        assert_eq!(msa.coverage_adjusted_distance("frag1", "full1"), Some(0.0));
+        assert_eq!(msa.coverage_adjusted_distance("frag1", "gaps"), None);
+        assert_eq!(msa.coverage_adjusted_distance("frag1", "missing"), None);
+    }
+
+    #[test]
+    fn msa_minimum_spanning_tree() {
+        let mut msa = MSA::default();
+        assert!(msa.minimum_spanning_tree(true).is_empty());
+
+        msa.push_record("id1", "ACGTACGT");
+        assert!(msa.minimum_spanning_tree(true).is_empty());
+
+        msa.push_record("id2", "TGCAACGA");
+        msa.push_record("id3", "ACGAACGT");
+        let edges = msa.minimum_spanning_tree(true);
+        assert_eq!(
+            edges,
+            vec![
+                ("id1".to_string(), "id3".to_string(), 1.0),
+                ("id3".to_string(), "id2".to_string(), 4.0),
+            ]
+        );
+
+        msa.push_record("id4", "ACG--CGT");
+        assert_eq!(msa.minimum_spanning_tree(true)[0].2, 0.0);
+        assert_eq!(msa.minimum_spanning_tree(false)[0].2, 1.0);
+    }
+
+    #[test]
+    fn msa_internal_stop_codons() {
+        let mut msa = MSA::default();
+        msa.push_record("clean", "ATGAAAGGC---TAA");
+        msa.push_record("premature", "ATGTGAGGCTAG---");
+        msa.push_record("trailing", "ATGAAAGGCTAA---");
+        msa.push_record("gaps", "---------------");
+        let stops = msa.internal_stop_codons(&CodonTable::standard());
+        assert_eq!(stops, vec![("premature".to_string(), 1)]);
+        assert!(msa
+            .internal_stop_codons(&CodonTable::from_ncbi(2).unwrap())
+            .is_empty());
+
+        let mut msa = MSA::default();
+        msa.push_record("trailing", "ATGTAA~~~");
+        msa.set_gaps(GapSet::new(&['~']));
+        assert!(msa.internal_stop_codons(&CodonTable::standard()).is_empty());
+    }
+
+    #[test]
+    fn msa_adjacent_column_mi() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "GCAGCA");
+        msa.push_record("id2", "TTATTG");
+        msa.push_record("id3", "GCGTTA");
+        msa.push_record("id4", "TTGGCG");
+        let mi = msa.adjacent_column_mi(true);
+        assert_eq!(mi.len(), 5);
+        for (col, value) in mi.iter().enumerate() {
+            let expected = if col % 3 == 0 { 1.0 } else { 0.0 };
+            assert!((value - expected).abs() < 1e-12, "column {}", col);
+        }
+
+        msa.push_record("id5", "G-AGCA");
+        assert!((msa.adjacent_column_mi(false)[0] - 0.970_950_594_454_668_6).abs() < 1e-12);
+        assert_eq!(msa.adjacent_column_mi(true)[0], 1.0);
+        assert!(MSA::default().adjacent_column_mi(true).is_empty());
+    }
+
+    #[test]
+    fn msa_volume_variance() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "GLa-X");
+        msa.push_record("id2", "WLA--");
+        msa.push_record("id3", "GlA--");
+        let variance = msa.volume_variance();
+        assert!(variance[0] > variance[1]);
+        assert!((variance[0] - 2.0 * 167.7f64.powi(2) / 9.0).abs() < 1e-9);
+        assert_eq!(variance[1], 0.0);
+        assert!(variance[2].abs() < 1e-9);
+        assert_eq!(variance[3], 0.0);
+        assert_eq!(variance[4], 0.0);
+    }
+
+    #[test]
+    fn msa_alphabet() {
+        let mut msa = MSA::default();
+        assert_eq!(msa.guess_alphabet(), Alphabet::Unknown);
+        msa.push_record("id1", "-----");
+        assert_eq!(msa.guess_alphabet(), Alphabet::Unknown);
+        assert!(msa.validate_alphabet(Alphabet::Dna).is_ok());
+
+        msa.push_record("id2", "ACGTN");
+        msa.push_record("id3", "acgu.");
+        assert_eq!(msa.guess_alphabet(), Alphabet::Dna);
+        assert!(matches!(
+            msa.validate_alphabet(Alphabet::Dna),
+            Err(Error::InvalidResidue { id, column: 3, residue: 'u' }) if id == "id3"
+        ));
+        assert!(msa.validate_alphabet(Alphabet::Protein).is_ok());
+        assert!(msa.validate_alphabet(Alphabet::Unknown).is_ok());
+
+        msa.push_record("id4", "MKVLé");
+        assert_eq!(msa.guess_alphabet(), Alphabet::Protein);
+        assert!(matches!(
+            msa.validate_alphabet(Alphabet::Protein),
+            Err(Error::InvalidResidue { residue: 'é', .. })
+        ));
+    }
+
+    #[test]
+    fn msa_stats_json() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACG-");
+        msa.push_record("id2", "ACGU");
+        msa.push_record("id3", "AUG-");
+
+        let stats: serde_json::Value = serde_json::from_str(&msa.stats_json()).unwrap();
+        assert_eq!(stats["num_rows"], 3);
+        assert_eq!(stats["num_columns"], 4);
+        assert_eq!(stats["gap_fraction"], 1.0 / 6.0);
+        assert!((stats["mean_identity"].as_f64().unwrap() - 7.0 / 9.0).abs() < 1e-12);
+        assert_eq!(stats["conservation"][1], 2.0 / 3.0);
+        assert_eq!(stats["entropy"].as_array().unwrap().len(), 4);
+        assert_eq!(stats["alphabet"], "rna");
+
+        let stats: serde_json::Value = serde_json::from_str(&MSA::default().stats_json()).unwrap();
+        assert!(stats["mean_identity"].is_null());
+        assert_eq!(stats["alphabet"], "unknown");
+    }
+
+    #[test]
+    fn msa_conservation_statistics() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AA-");
+        msa.push_record("id2", "AC.");
+        msa.push_record("id3", "AC-");
+        msa.push_record("id4", "-G-");
+
+        assert_eq!(msa.conservation(), vec![1.0, 0.5, 0.0]);
+        assert_eq!(msa.entropy(), vec![0.0, 1.5, 0.0]);
+
+        let frequencies = msa.column_frequencies(1);
+        assert_eq!(frequencies[&'C'], 0.5);
+        assert_eq!(frequencies[&'A'], 0.25);
+        assert!(msa.column_frequencies(2).is_empty());
+        assert!(msa.column_frequencies(3).is_empty());
+    }
+
+    #[test]
+    fn msa_entropy_gradient_boundary() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "AAAAAACGTA");
+        msa.push_record("id2", "AAAAACGTAC");
+        msa.push_record("id3", "AAAAAGTACG");
+        msa.push_record("id4", "AAAAATACGT");
+
+        let gradient = msa.entropy_gradient(2.0, 1);
+        assert_eq!(gradient.len(), 10);
+        assert_eq!(gradient[..4], [0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(gradient[4], 1.0);
+        assert_eq!(gradient[5], 1.0);
+        assert_eq!(gradient[7..], [0.0, 0.0, 0.0]);
+
+        let smoothed = msa.entropy_gradient(2.0, 3);
+        let peak = smoothed
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert!((4..=5).contains(&peak));
     }
 }
@@ -0,0 +1,108 @@
+use crate::scoring::ScoringMatrix;
+use crate::trace::Operation;
+
+/// Result of a pairwise alignment
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairwiseAlignment {
+    /// Alignment score
+    score: i32,
+
+    /// Aligned positions in the first and second sequence, `None` for a gap
+    path: Vec<(Option<usize>, Option<usize>)>,
+}
+
+impl PairwiseAlignment {
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn path(&self) -> &[(Option<usize>, Option<usize>)] {
+        &self.path
+    }
+
+    /// Returns the pairs of positions aligned to each other, gaps excluded
+    pub fn aligned_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.path.iter().filter_map(|&(i, j)| Some((i?, j?)))
+    }
+}
+
+/// Globally aligns `a` and `b` (Needleman-Wunsch) with the substitution
+/// scores and linear gap penalty of `matrix`. Ties prefer substitutions,
+/// then gaps in `b`, then gaps in `a`, so the result is deterministic.
+/// # Example
+/// ```
+/// use align_rs::pairwise::global_align;
+/// use align_rs::scoring::ScoringMatrix;
+///
+/// let alignment = global_align(b"ACGT", b"AGT", &ScoringMatrix::new(1, -1, -1));
+/// assert_eq!(alignment.score(), 2);
+/// assert_eq!(alignment.aligned_pairs().collect::<Vec<_>>(), vec![(0, 0), (2, 1), (3, 2)]);
+/// ```
+pub fn global_align(a: &[u8], b: &[u8], matrix: &ScoringMatrix) -> PairwiseAlignment {
+    let op = Operation::start("global_align");
+    let (n, m) = (a.len(), b.len());
+    let gap = matrix.gap();
+    let width = m + 1;
+
+    let mut scores = vec![0i32; (n + 1) * width];
+    for (i, cell) in scores.iter_mut().step_by(width).enumerate() {
+        *cell = i as i32 * gap;
+    }
+    for (j, cell) in scores[..width].iter_mut().enumerate() {
+        *cell = j as i32 * gap;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let diagonal = scores[(i - 1) * width + j - 1] + matrix.score(a[i - 1], b[j - 1]);
+            let up = scores[(i - 1) * width + j] + gap;
+            let left = scores[i * width + j - 1] + gap;
+            scores[i * width + j] = diagonal.max(up).max(left);
+        }
+    }
+
+    // Trace back from the bottom right corner
+    let mut path = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let current = scores[i * width + j];
+        if i > 0
+            && j > 0
+            && current == scores[(i - 1) * width + j - 1] + matrix.score(a[i - 1], b[j - 1])
+        {
+            path.push((Some(i - 1), Some(j - 1)));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && current == scores[(i - 1) * width + j] + gap {
+            path.push((Some(i - 1), None));
+            i -= 1;
+        } else {
+            path.push((None, Some(j - 1)));
+            j -= 1;
+        }
+    }
+    path.reverse();
+
+    op.finish_with(2, path.len());
+    PairwiseAlignment {
+        score: scores[n * width + m],
+        path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_align_gaps() {
+        let matrix = ScoringMatrix::new(2, -1, -2);
+        let alignment = global_align(b"ACGTAC", b"CGTACA", &matrix);
+        assert_eq!(alignment.score(), 6);
+        assert_eq!(alignment.path()[0], (Some(0), None));
+        assert_eq!(alignment.path()[6], (None, Some(5)));
+
+        let empty = global_align(b"", b"AC", &matrix);
+        assert_eq!(empty.score(), -4);
+        assert_eq!(empty.path(), &[(None, Some(0)), (None, Some(1))]);
+    }
+}
@@ -1,12 +1,71 @@
-use std::error::Error;
-use std::io::BufRead;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
 
+use flate2::bufread::MultiGzDecoder;
 use regex::Regex;
 
-use crate::msa::MSA;
+use crate::error::Error;
+use crate::msa::{AlignmentSummary, ColumnTrack, MSA};
+use crate::record::Record;
+use crate::trace::{self, Operation};
+
+/// Alignment file formats understood by [`Reader::read`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Clustal,
+    Fasta,
+    Nexus,
+    Phylip,
+    Stockholm,
+}
+
+/// How [`Reader::read_a3m`] handles the insert states of A3M files,
+/// written in lowercase and not aligned between records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertHandling {
+    /// Drop the inserts, keeping the match columns only. The record as
+    /// read is kept as its "a3m" annotation.
+    Strip,
+
+    /// Keep the inserts, padding the other records with `.` in the insert
+    /// columns (A2M layout)
+    Pad,
+}
 
 pub struct Reader<R> {
     inner: R,
+
+    /// Check CLUSTAL residue numbers against the parsed sequences
+    check_numbering: bool,
+
+    /// Store the guessed alphabet of CLUSTAL alignments as an annotation
+    detect_alphabet: bool,
+}
+
+/// Magic bytes starting every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl Reader<Box<dyn BufRead>> {
+    /// Opens the file at `path` for reading, decompressing it on the fly if
+    /// it is gzipped. Compression is detected from the content of the file,
+    /// whatever its extension.
+    /// # Example
+    /// ```
+    /// use align_rs::reader::Reader;
+    ///
+    /// let msa = Reader::from_path("tests/clustalw.aln").unwrap().read_clustal().unwrap();
+    /// assert_eq!(msa.len(), 2);
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = BufReader::new(File::open(path)?);
+        let inner: Box<dyn BufRead> = match file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            true => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+            false => Box::new(file),
+        };
+        Ok(Reader::new(inner))
+    }
 }
 
 impl<R> Reader<R>
@@ -14,78 +73,1177 @@ where
     R: BufRead,
 {
     pub fn new(inner: R) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            check_numbering: false,
+            detect_alphabet: false,
+        }
+    }
+
+    /// Verify the residue numbers printed at the end of CLUSTAL lines
+    /// (`-SEQNOS=ON`) against the number of residues read so far. They are
+    /// always stripped from the sequences; this only controls validation.
+    pub fn check_numbering(mut self, check: bool) -> Self {
+        self.check_numbering = check;
+        self
+    }
+
+    /// Guess the alphabet of CLUSTAL alignments (see
+    /// [`MSA::guess_alphabet`]) and store it as the "alphabet" annotation
+    pub fn detect_alphabet(mut self, detect: bool) -> Self {
+        self.detect_alphabet = detect;
+        self
+    }
+
+    /// Reads an alignment in the given `format`
+    pub fn read(&mut self, format: Format) -> Result<MSA, Error> {
+        match format {
+            Format::Clustal => self.read_clustal(),
+            Format::Fasta => self.read_fasta(),
+            Format::Nexus => self.read_nexus(),
+            Format::Phylip => self.read_phylip(),
+            Format::Stockholm => self.read_stockholm(),
+        }
+    }
+
+    pub fn read_clustal(&mut self) -> Result<MSA, Error> {
+        let op = Operation::start("read_clustal");
+        let mut msa = read_clustal(&mut self.inner, self.check_numbering)?;
+        if self.detect_alphabet {
+            let alphabet = msa.guess_alphabet();
+            msa.add_annotation("alphabet".to_string(), alphabet.to_string());
+        }
+        op.finish(&msa);
+        Ok(msa)
+    }
+
+    /// Returns an iterator over the blocks of a CLUSTAL alignment, to process
+    /// alignments too large to be held in memory. Fails if the header is
+    /// not recognised. [`Reader::read_clustal`] reads every block at once.
+    /// # Example
+    /// ```
+    /// use align_rs::reader::Reader;
+    ///
+    /// let input = "CLUSTAL W (1.83)\n\nid1  ACGT\nid2  AC-T\n     ** *\n\nid1  GG\nid2  GC\n";
+    /// let mut reader = Reader::new(input.as_bytes());
+    /// let blocks: Vec<_> = reader.blocks_clustal().unwrap().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(blocks.len(), 2);
+    /// assert_eq!(blocks[1].get(1).unwrap().sequence(), "GC");
+    /// ```
+    pub fn blocks_clustal(&mut self) -> Result<ClustalBlocks<'_, R>, Error> {
+        ClustalBlocks::new(&mut self.inner, self.check_numbering)
+    }
+
+    /// Returns an iterator over the records of a FASTA file, to process files
+    /// too large to be held in memory. [`Reader::read_fasta`] reads every
+    /// record at once.
+    /// # Example
+    /// ```
+    /// use align_rs::reader::Reader;
+    ///
+    /// let mut reader = Reader::new(&b">id1 first\nAC\nGT\n>id2\nAC\n"[..]);
+    /// let lengths: Vec<usize> = reader.records_fasta().map(|x| x.unwrap().len()).collect();
+    /// assert_eq!(lengths, vec![4, 2]);
+    /// ```
+    pub fn records_fasta(&mut self) -> FastaRecords<'_, R> {
+        FastaRecords::new(&mut self.inner)
+    }
+
+    pub fn read_fasta(&mut self) -> Result<MSA, Error> {
+        let op = Operation::start("read_fasta");
+        let msa = read_fasta(&mut self.inner)?;
+        op.finish(&msa);
+        Ok(msa)
+    }
+
+    /// Reads an A3M file, as written by HH-suite: FASTA records whose
+    /// uppercase residues and `-` are match columns and whose lowercase
+    /// residues and `.` are insertions. Lines starting with `#` before the
+    /// first record are skipped. Fails if records do not have the same
+    /// number of match columns.
+    pub fn read_a3m(&mut self, inserts: InsertHandling) -> Result<MSA, Error> {
+        let op = Operation::start("read_a3m");
+        let msa = read_a3m(&mut self.inner, inserts)?;
+        op.finish(&msa);
+        Ok(msa)
+    }
+
+    /// Returns an iterator over the alignment blocks of a MAF file, as
+    /// written by whole-genome aligners. The fields of each `a` line become
+    /// annotations of its block, and the coordinates of each `s` line, as
+    /// written, the "start", "size", "strand" and "srcSize" annotations of
+    /// its record. Comment, `i`, `e` and `q` lines are skipped.
+    /// # Example
+    /// ```
+    /// use align_rs::reader::Reader;
+    ///
+    /// let input = "##maf version=1\na score=5.0\ns hg.chr1 10 3 + 100 AC-G\ns mm.chr2 7 4 - 90 ACTG\n";
+    /// let mut reader = Reader::new(input.as_bytes());
+    /// let block = reader.read_maf_blocks().next().unwrap().unwrap();
+    /// assert_eq!(block.get_annotation("score").unwrap(), "5.0");
+    /// assert_eq!(block.get_record_annotation("mm.chr2", "strand").unwrap(), "-");
+    /// ```
+    pub fn read_maf_blocks(&mut self) -> MafBlocks<'_, R> {
+        MafBlocks {
+            inner: &mut self.inner,
+            buf: String::new(),
+            line_no: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Reads the alignment of the `DATA` or `CHARACTERS` block of a NEXUS
+    /// file, either interleaved or not. Other blocks and comments in square
+    /// brackets are skipped, and quoted taxon names are unquoted. The
+    /// `DATATYPE`, `GAP` and `MISSING` formats become the "datatype", "gap"
+    /// and "missing" annotations. Fails if the matrix does not match the
+    /// declared `NTAX` and `NCHAR`.
+    /// # Example
+    /// ```
+    /// use align_rs::reader::Reader;
+    ///
+    /// let input = "#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=4;\nFORMAT DATATYPE=DNA;\nMATRIX\n'id 1' AC-T\nid2 ACGT\n;\nEND;\n";
+    /// let msa = Reader::new(input.as_bytes()).read_nexus().unwrap();
+    /// assert_eq!(msa.get_record("id 1").unwrap().sequence(), "AC-T");
+    /// assert_eq!(msa.get_annotation("datatype").unwrap(), "DNA");
+    /// ```
+    pub fn read_nexus(&mut self) -> Result<MSA, Error> {
+        let op = Operation::start("read_nexus");
+        let msa = read_nexus(&mut self.inner)?;
+        op.finish(&msa);
+        Ok(msa)
+    }
+
+    pub fn read_phylip(&mut self) -> Result<MSA, Error> {
+        let op = Operation::start("read_phylip");
+        let msa = read_phylip(&mut self.inner)?;
+        op.finish(&msa);
+        Ok(msa)
+    }
+
+    pub fn read_stockholm(&mut self) -> Result<MSA, Error> {
+        let op = Operation::start("read_stockholm");
+        let msa = read_stockholm(&mut self.inner)?;
+        op.finish(&msa);
+        Ok(msa)
+    }
+}
+
+/// Reads every file of `paths` in `format` and returns its path, as
+/// displayed, with the summary of its alignment. The first failure is
+/// returned as [`Error::InFile`], naming the file.
+/// # Example
+/// ```
+/// use align_rs::reader::{summarize_files, Format};
+///
+/// let rows = summarize_files(&["tests/clustalw.aln"], Format::Clustal).unwrap();
+/// assert_eq!(rows[0].0, "tests/clustalw.aln");
+/// assert_eq!(rows[0].1.num_rows(), 2);
+/// ```
+pub fn summarize_files<P>(
+    paths: &[P],
+    format: Format,
+) -> Result<Vec<(String, AlignmentSummary)>, Error>
+where
+    P: AsRef<Path>,
+{
+    paths
+        .iter()
+        .map(|path| {
+            let name = path.as_ref().display().to_string();
+            File::open(path)
+                .map_err(Error::from)
+                .and_then(|file| Reader::new(BufReader::new(file)).read(format))
+                .map(|msa| (name.clone(), msa.summary()))
+                .map_err(|error| Error::InFile {
+                    path: name,
+                    error: Box::new(error),
+                })
+        })
+        .collect()
+}
+
+fn read_clustal<R>(reader: &mut R, check_numbering: bool) -> Result<MSA, Error>
+where
+    R: BufRead,
+{
+    let mut blocks = ClustalBlocks::new(reader, check_numbering)?;
+    let mut msa = MSA::default();
+    for (name, value) in blocks.annotations() {
+        msa.add_annotation(name.to_string(), value.to_string());
+    }
+
+    // Blocks without a conservation line still take their columns in it
+    let mut cons = String::new();
+    let mut has_cons = false;
+    for block in blocks.by_ref() {
+        let block = block?;
+        pad_to(&mut cons, msa.col_len());
+        if let Some(block_cons) = block.get_column_annotation("cons") {
+            cons.push_str(block_cons);
+            has_cons = true;
+        }
+        for record in &block {
+            msa.push_record(record.id(), record.sequence());
+            for (name, value) in record.annotations() {
+                msa.push_record_annotation(record.id(), name, value);
+            }
+        }
     }
 
-    pub fn read_clustal(&mut self) -> Result<MSA, Box<dyn Error>> {
-        read_clustal(&mut self.inner)
+    if has_cons {
+        pad_to(&mut cons, msa.col_len());
+        msa.set_column_track("cons", ColumnTrack::Chars(cons))?;
+    }
+    msa.validate()?;
+    Ok(msa)
+}
+
+/// Iterator over the blocks of an interleaved CLUSTAL alignment, created by
+/// [`Reader::blocks_clustal`]. Each block is an [`MSA`] holding the residues
+/// of that block only, with its conservation line as the "cons" column
+/// annotation when present. Only the current block is kept in memory.
+/// Iteration stops after the first error.
+pub struct ClustalBlocks<'a, R> {
+    inner: &'a mut R,
+    buf: String,
+    line_no: usize,
+    check_numbering: bool,
+
+    /// Program and version read from the header
+    annotations: HashMap<String, String>,
+
+    /// Number of residues read so far for each sequence
+    residues: HashMap<String, usize>,
+
+    /// Sequence names carrying a residue range (`-SEQNO_RANGE=ON`)
+    range_re: Regex,
+
+    done: bool,
+}
+
+impl<'a, R> ClustalBlocks<'a, R>
+where
+    R: BufRead,
+{
+    /// Reads the header line, the blocks being parsed on demand
+    fn new(inner: &'a mut R, check_numbering: bool) -> Result<Self, Error> {
+        let mut buf = String::new();
+        inner.read_line(&mut buf)?;
+
+        let known_header = ["CLUSTAL", "PROBCONS", "MUSCLE", "MSAPROBS", "Kalign"];
+        let mut annotations = HashMap::new();
+        if let Some(header) = known_header.iter().find(|&&h| buf.starts_with(h)) {
+            trace::debug!(program = header, "recognised CLUSTAL header");
+            annotations.insert("program".to_string(), header.to_string());
+        } else {
+            return Err(Error::InvalidHeader {
+                found: buf.trim_end().to_string(),
+            });
+        }
+
+        // Parsing program version
+        let version_re = Regex::new(r"(\d+(?:\.\d+)+)").unwrap();
+        if let Some(version) = version_re.captures(&buf) {
+            annotations.insert("version".to_string(), version[1].to_string());
+        }
+
+        Ok(ClustalBlocks {
+            inner,
+            buf,
+            line_no: 1,
+            check_numbering,
+            annotations,
+            residues: HashMap::new(),
+            range_re: Regex::new(r"/(\d+)-(\d+)$").unwrap(),
+            done: false,
+        })
     }
 
-    pub fn read_stockholm(&mut self) -> Result<MSA, Box<dyn Error>> {
-        read_stockholm(&mut self.inner)
+    /// Returns the "program" and "version" annotations of the header
+    pub fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    /// Adds a sequence line to `block`
+    fn push_line(&mut self, block: &mut MSA, line: &str) -> Result<(), Error> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            return Err(Error::ParseError {
+                line: self.line_no,
+                message: format!("expected a sequence name and residues, found {:?}", line),
+            });
+        }
+        block.push_record(fields[0], fields[1]);
+
+        let range = self.range_re.captures(fields[0]);
+        let previous = self.residues.get(fields[0]).copied();
+        if let (None, Some(range)) = (previous, &range) {
+            block.push_record_annotation(fields[0], "start", &range[1]);
+            block.push_record_annotation(fields[0], "end", &range[2]);
+        }
+        let residues =
+            previous.unwrap_or(0) + fields[1].bytes().filter(|b| !b"-.".contains(b)).count();
+        self.residues.insert(fields[0].to_string(), residues);
+
+        if let (true, Some(number)) = (self.check_numbering, fields.get(2)) {
+            // Numbers count from the 1-based start of a name/start-end range
+            let offset = match &range {
+                Some(range) => match range[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|x| x.checked_sub(1))
+                {
+                    Some(offset) => offset,
+                    None => {
+                        return Err(Error::ParseError {
+                            line: self.line_no,
+                            message: format!(
+                                "invalid start {} in the name of {}",
+                                &range[1], fields[0]
+                            ),
+                        })
+                    }
+                },
+                None => 0,
+            };
+            let expected = offset + residues;
+            if number.parse::<usize>().ok() != Some(expected) {
+                return Err(Error::ParseError {
+                    line: self.line_no,
+                    message: format!(
+                        "residue number {} of {} does not match the {} residues read",
+                        number, fields[0], expected
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn next_block(&mut self) -> Result<Option<MSA>, Error> {
+        let mut block = MSA::default();
+        // Start column and width of the residues, set by the first sequence
+        // line of the block
+        let mut layout: Option<(usize, usize)> = None;
+        loop {
+            self.buf.clear();
+            if self.inner.read_line(&mut self.buf)? == 0 {
+                return Ok(Some(block).filter(|x| !x.is_empty()));
+            }
+            self.line_no += 1;
+            let line = self.buf.trim_end_matches(&['\r', '\n'][..]).to_string();
+
+            if line.trim().is_empty() && !line.starts_with(' ') {
+                // End of block: a conservation line stripped of all its
+                // spaces looks like a blank line
+                if layout.is_some() {
+                    return Ok(Some(block));
+                }
+            } else if !line.starts_with(' ') {
+                if layout.is_none() {
+                    let name_len = line.split_whitespace().next().unwrap_or("").len();
+                    let residues = line[name_len..].trim_start();
+                    let width = residues.split_whitespace().next().unwrap_or("").len();
+                    layout = Some((line.len() - residues.len(), width));
+                }
+                self.push_line(&mut block, &line)?;
+            } else if let Some((start, width)) = layout {
+                // Conservation line, possibly missing its trailing spaces
+                let mut cons: String = line
+                    .get(start..)
+                    .unwrap_or("")
+                    .chars()
+                    .take(width)
+                    .collect();
+                pad_to(&mut cons, width);
+                block.set_column_track("cons", ColumnTrack::Chars(cons))?;
+                return Ok(Some(block));
+            }
+        }
     }
 }
 
-fn read_clustal<R>(reader: &mut R) -> Result<MSA, Box<dyn Error>>
+impl<'a, R> Iterator for ClustalBlocks<'a, R>
+where
+    R: BufRead,
+{
+    type Item = Result<MSA, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let block = self.next_block();
+        if !matches!(block, Ok(Some(_))) {
+            self.done = true;
+        }
+        block.transpose()
+    }
+}
+
+/// Pad `s` with spaces up to `len` characters
+fn pad_to(s: &mut String, len: usize) {
+    let missing = len.saturating_sub(s.chars().count());
+    s.push_str(&" ".repeat(missing));
+}
+
+fn read_fasta<R>(reader: &mut R) -> Result<MSA, Error>
 where
     R: BufRead,
 {
     let mut msa = MSA::default();
+    for record in FastaRecords::new(reader) {
+        let record = record?;
+        msa.push_record(record.id(), record.sequence());
+        for (name, value) in record.annotations() {
+            msa.push_record_annotation(record.id(), name, value);
+        }
+    }
+
+    // Aligned FASTA must be rectangular
+    msa.validate()?;
+    Ok(msa)
+}
+
+/// Iterator over the records of a FASTA file, created by
+/// [`Reader::records_fasta`]. Only the current record is kept in memory and
+/// the sequences are not checked to have the same length. Iteration stops
+/// after the first error.
+pub struct FastaRecords<'a, R> {
+    inner: &'a mut R,
+    buf: String,
+    line_no: usize,
+
+    /// Record whose sequence lines are being read
+    pending: Option<Record>,
+
+    /// Error found after a complete record, reported on the next call
+    error: Option<Error>,
+
+    done: bool,
+}
+
+impl<'a, R> FastaRecords<'a, R>
+where
+    R: BufRead,
+{
+    fn new(inner: &'a mut R) -> Self {
+        FastaRecords {
+            inner,
+            buf: String::new(),
+            line_no: 0,
+            pending: None,
+            error: None,
+            done: false,
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>, Error> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        loop {
+            self.buf.clear();
+            if self.inner.read_line(&mut self.buf)? == 0 {
+                return Ok(self.pending.take());
+            }
+            self.line_no += 1;
+            let line = self.buf.trim_end();
+            if let Some(header) = line.strip_prefix('>') {
+                let mut parts = header.trim_start().splitn(2, char::is_whitespace);
+                let id = parts.next().unwrap_or("");
+                if id.is_empty() {
+                    let err = Error::ParseError {
+                        line: self.line_no,
+                        message: "FASTA header without a sequence ID".to_string(),
+                    };
+                    // The record read before is complete and comes first
+                    return match self.pending.take() {
+                        Some(previous) => {
+                            self.error = Some(err);
+                            Ok(Some(previous))
+                        }
+                        None => Err(err),
+                    };
+                }
+                let mut record = Record::new(id, "");
+                if let Some(description) = parts.next().map(str::trim).filter(|x| !x.is_empty()) {
+                    record.push_annotation("description", description);
+                }
+                if let Some(previous) = self.pending.replace(record) {
+                    return Ok(Some(previous));
+                }
+            } else if !line.is_empty() {
+                match self.pending.as_mut() {
+                    Some(record) => record.push_seq(line),
+                    None => {
+                        return Err(Error::ParseError {
+                            line: self.line_no,
+                            message: "sequence data before the first FASTA header".to_string(),
+                        })
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, R> Iterator for FastaRecords<'a, R>
+where
+    R: BufRead,
+{
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.next_record();
+        if !matches!(record, Ok(Some(_))) {
+            self.done = true;
+        }
+        record.transpose()
+    }
+}
+
+fn read_a3m<R>(reader: &mut R, inserts: InsertHandling) -> Result<MSA, Error>
+where
+    R: BufRead,
+{
+    // Skip the "#A3M#" line and other comments heading the file
+    let mut buf = String::new();
+    while reader.fill_buf()?.first() == Some(&b'#') {
+        reader.read_line(&mut buf)?;
+    }
+
+    let mut records: Vec<(Record, Vec<char>, Vec<String>)> = Vec::new();
+    for record in FastaRecords::new(reader) {
+        let record = record?;
+        let (matches, record_inserts) = split_a3m(record.sequence());
+        match records.first() {
+            Some((_, expected, _)) if matches.len() != expected.len() => {
+                return Err(Error::UnequalLength {
+                    id: record.id().to_string(),
+                    expected: expected.len(),
+                    found: matches.len(),
+                })
+            }
+            _ => records.push((record, matches, record_inserts)),
+        }
+    }
+
+    // Longest insert before each match column, and after the last one
+    let match_columns = records.first().map_or(0, |(_, matches, _)| matches.len());
+    let mut widths = vec![0usize; match_columns + 1];
+    if inserts == InsertHandling::Pad {
+        for (_, _, record_inserts) in &records {
+            for (width, insert) in widths.iter_mut().zip(record_inserts) {
+                *width = (*width).max(insert.chars().count());
+            }
+        }
+    }
+
+    let mut msa = MSA::default();
+    for (record, matches, record_inserts) in &records {
+        let mut sequence = String::new();
+        for (column, (width, insert)) in widths.iter().zip(record_inserts).enumerate() {
+            if inserts == InsertHandling::Pad {
+                sequence.push_str(insert);
+                sequence.push_str(&".".repeat(width - insert.chars().count()));
+            }
+            if let Some(&residue) = matches.get(column) {
+                sequence.push(residue);
+            }
+        }
+        msa.push_record(record.id(), &sequence);
+        for (name, value) in record.annotations() {
+            msa.push_record_annotation(record.id(), name, value);
+        }
+        if inserts == InsertHandling::Strip {
+            msa.push_record_annotation(record.id(), "a3m", record.sequence());
+        }
+    }
+    Ok(msa)
+}
+
+/// Iterator over the alignment blocks of a MAF file, created by
+/// [`Reader::read_maf_blocks`]. Only the current block is kept in memory.
+/// Iteration stops after the first error.
+pub struct MafBlocks<'a, R> {
+    inner: &'a mut R,
+    buf: String,
+    line_no: usize,
+
+    /// Annotations of a block whose `a` line ended the previous block
+    pending: Option<HashMap<String, String>>,
+
+    done: bool,
+}
+
+impl<'a, R> MafBlocks<'a, R>
+where
+    R: BufRead,
+{
+    fn next_block(&mut self) -> Result<Option<MSA>, Error> {
+        let mut annotations = self.pending.take();
+        let mut records = Vec::new();
+        loop {
+            self.buf.clear();
+            if self.inner.read_line(&mut self.buf)? == 0 {
+                break;
+            }
+            self.line_no += 1;
+            let line = self.buf.trim_end();
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("a") => {
+                    let block: HashMap<String, String> = fields
+                        .filter_map(|x| x.split_once('='))
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect();
+                    if annotations.is_some() {
+                        self.pending = Some(block);
+                        break;
+                    }
+                    annotations = Some(block);
+                }
+                Some("s") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if annotations.is_none() || fields.len() != 6 {
+                        return Err(Error::ParseError {
+                            line: self.line_no,
+                            message: "expected an \"s\" line of 7 fields in a block".to_string(),
+                        });
+                    }
+                    let mut record = Record::new(fields[0], fields[5]);
+                    for (name, value) in ["start", "size", "strand", "srcSize"]
+                        .iter()
+                        .zip(&fields[1..5])
+                    {
+                        record.push_annotation(name, value);
+                    }
+                    records.push(record);
+                }
+                None if annotations.is_some() => break,
+                _ => {}
+            }
+        }
+
+        match annotations {
+            Some(annotations) => {
+                let msa = MSA::new(records, annotations, HashMap::new());
+                msa.validate()?;
+                Ok(Some(msa))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, R> Iterator for MafBlocks<'a, R>
+where
+    R: BufRead,
+{
+    type Item = Result<MSA, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let block = self.next_block();
+        if !matches!(block, Ok(Some(_))) {
+            self.done = true;
+        }
+        block.transpose()
+    }
+}
+
+/// Split an A3M sequence into its match residues and the inserts before
+/// each of them, plus the one after the last
+fn split_a3m(sequence: &str) -> (Vec<char>, Vec<String>) {
+    let mut matches = Vec::new();
+    let mut inserts = vec![String::new()];
+    for c in sequence.chars() {
+        if c.is_ascii_lowercase() || c == '.' {
+            inserts.last_mut().unwrap().push(c);
+        } else {
+            matches.push(c);
+            inserts.push(String::new());
+        }
+    }
+    (matches, inserts)
+}
+
+fn read_phylip<R>(reader: &mut R) -> Result<MSA, Error>
+where
+    R: BufRead,
+{
     let reader = Reader::new(reader);
 
-    // Parsing program name
+    // Header line gives the number of sequences and of columns
     let mut buf = String::new();
     reader.inner.read_line(&mut buf)?;
+    let dims: Vec<usize> = buf
+        .split_whitespace()
+        .take(2)
+        .filter_map(|x| x.parse::<usize>().ok())
+        .collect();
+    let (ntax, nchar) = match dims[..] {
+        [ntax, nchar] => (ntax, nchar),
+        _ => {
+            return Err(Error::InvalidHeader {
+                found: buf.trim_end().to_string(),
+            })
+        }
+    };
+
+    // Non-blank lines along with their line number
+    let mut lines = Vec::new();
+    let mut line_no: usize = 1;
+    buf.clear();
+    while reader.inner.read_line(&mut buf)? != 0 {
+        line_no += 1;
+        if !buf.trim().is_empty() {
+            lines.push((line_no, buf.trim_end().to_string()));
+        }
+        buf.clear();
+    }
+    if ntax == 0 && !lines.is_empty() {
+        return Err(Error::ParseError {
+            line: 1,
+            message: format!(
+                "PHYLIP header declares 0 sequences but data starts on line {}",
+                lines[0].0
+            ),
+        });
+    }
 
-    let known_header = ["CLUSTAL", "PROBCONS", "MUSCLE", "MSAPROBS", "Kalign"];
+    // A sequential file read as interleaved (or the reverse), or a strict
+    // file read with whitespace-delimited names, never yields the declared
+    // dimensions, so the first layout that validates wins
+    parse_phylip(&lines, ntax, nchar, false, false)
+        .or_else(|_| parse_phylip(&lines, ntax, nchar, true, false))
+        .or_else(|e| {
+            parse_phylip(&lines, ntax, nchar, false, true)
+                .or_else(|_| parse_phylip(&lines, ntax, nchar, true, true))
+                .map_err(|_| e)
+        })
+}
+
+/// Width of the name field in strict PHYLIP
+const PHYLIP_NAME_WIDTH: usize = 10;
 
-    if let Some(header) = known_header.iter().find(|&&h| buf.starts_with(h)) {
-        msa.add_annotation("program".to_string(), header.to_string());
+/// Parse the data lines of a PHYLIP file using the given layout
+fn parse_phylip(
+    lines: &[(usize, String)],
+    ntax: usize,
+    nchar: usize,
+    interleaved: bool,
+    strict: bool,
+) -> Result<MSA, Error> {
+    let residues = |s: &str| -> String { s.split_whitespace().collect() };
+    let mut msa = MSA::default();
+    let mut ids: Vec<&str> = Vec::with_capacity(ntax);
+    let mut lines = lines.iter();
+
+    if interleaved {
+        // The first block names every sequence, the following ones only
+        // carry residues in the same order
+        for (line_no, line) in lines.by_ref().take(ntax) {
+            let (id, sequence) = split_phylip_name(line, *line_no, strict)?;
+            ids.push(id);
+            msa.push_record(id, &residues(sequence));
+        }
+        for (i, (line_no, line)) in lines.enumerate() {
+            let id = ids[i % ntax];
+            let sequence = match split_phylip_name(line, *line_no, strict) {
+                Ok((name, sequence)) if name == id => sequence,
+                _ => line,
+            };
+            msa.push_record(id, &residues(sequence));
+        }
     } else {
-        return Err(format!(
-            "The header is not a recognised CLUSTAL header: {}",
-            known_header.join(",")
-        )
-        .into());
+        while let Some((line_no, line)) = lines.next() {
+            let (id, sequence) = split_phylip_name(line, *line_no, strict)?;
+            let mut sequence = residues(sequence);
+            while sequence.len() < nchar {
+                match lines.next() {
+                    Some((_, line)) => sequence.push_str(&residues(line)),
+                    None => break,
+                }
+            }
+            ids.push(id);
+            msa.push_record(id, &sequence);
+        }
     }
 
-    // Parsing program version
-    let version_re = Regex::new(r"(\d+(?:\.\d+)+)").unwrap();
-    if let Some(version) = version_re.captures(&buf) {
-        msa.add_annotation("version".to_string(), version[1].to_string());
+    if msa.len() != ntax || ids.len() != ntax {
+        return Err(Error::ParseError {
+            line: 1,
+            message: format!(
+                "PHYLIP header declares {} sequences but {} were found",
+                ntax,
+                ids.len()
+            ),
+        });
+    }
+    if let Some(record) = msa.records().iter().find(|x| x.len() != nchar) {
+        return Err(Error::UnequalLength {
+            id: record.id().to_string(),
+            expected: nchar,
+            found: record.len(),
+        });
     }
+    Ok(msa)
+}
 
-    // Handling rest of file
-    let mut start: usize = 0;
-    let mut end: usize = 0;
-    buf.clear();
+/// Split a PHYLIP line into the name and the residues. Strict names occupy
+/// exactly the first 10 characters, relaxed ones end at the first whitespace.
+fn split_phylip_name(line: &str, line_no: usize, strict: bool) -> Result<(&str, &str), Error> {
+    let split = if strict {
+        line.get(..PHYLIP_NAME_WIDTH)
+            .map(|name| (name.trim(), &line[PHYLIP_NAME_WIDTH..]))
+    } else {
+        line.trim_start().split_once(char::is_whitespace)
+    };
+    split.ok_or_else(|| Error::ParseError {
+        line: line_no,
+        message: "missing sequence name or residues".to_string(),
+    })
+}
 
-    while reader.inner.read_line(&mut buf)? != 0 {
-        if !buf.starts_with(' ') && buf != "\n" {
-            let fields: Vec<&str> = buf.split_whitespace().filter(|x| !x.is_empty()).collect();
-            start = fields[0].len() + buf[fields[0].len()..].find(fields[1]).unwrap_or(0);
-            end = start + fields[1].len();
-            msa.push_record(fields[0], fields[1]);
+/// A word of a NEXUS file, with the line it starts on
+struct NexusToken {
+    text: String,
+    line: usize,
+    quoted: bool,
+}
+
+/// Split a NEXUS file into words, quoted words and the `;` and `=`
+/// punctuation, dropping comments
+fn nexus_tokens(input: &str) -> Result<Vec<NexusToken>, Error> {
+    let mut tokens = Vec::new();
+    let mut current: Option<NexusToken> = None;
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            // Comments may nest and do not end the current word
+            '[' => {
+                let start = line;
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('[') => depth += 1,
+                        Some(']') => depth -= 1,
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => {
+                            return Err(Error::ParseError {
+                                line: start,
+                                message: "unterminated NEXUS comment".to_string(),
+                            })
+                        }
+                    }
+                }
+            }
+            '\'' => {
+                tokens.extend(current.take());
+                let mut token = NexusToken {
+                    text: String::new(),
+                    line,
+                    quoted: true,
+                };
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            token.text.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            token.text.push(c);
+                        }
+                        None => {
+                            return Err(Error::ParseError {
+                                line: token.line,
+                                message: "unterminated NEXUS quoted word".to_string(),
+                            })
+                        }
+                    }
+                }
+                tokens.push(token);
+            }
+            ';' | '=' => {
+                tokens.extend(current.take());
+                tokens.push(NexusToken {
+                    text: c.to_string(),
+                    line,
+                    quoted: false,
+                });
+            }
+            c if c.is_whitespace() => {
+                tokens.extend(current.take());
+                if c == '\n' {
+                    line += 1;
+                }
+            }
+            c => current
+                .get_or_insert_with(|| NexusToken {
+                    text: String::new(),
+                    line,
+                    quoted: false,
+                })
+                .text
+                .push(c),
+        }
+    }
+    tokens.extend(current.take());
+    Ok(tokens)
+}
+
+fn read_nexus<R>(reader: &mut R) -> Result<MSA, Error>
+where
+    R: BufRead,
+{
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    let header = input.lines().next().unwrap_or("").trim();
+    if !header.eq_ignore_ascii_case("#NEXUS") {
+        return Err(Error::InvalidHeader {
+            found: header.to_string(),
+        });
+    }
+
+    // Every command is a keyword followed by its arguments up to a `;`
+    let mut tokens = nexus_tokens(&input)?.into_iter().skip(1);
+    let mut in_data = false;
+    let mut ntax = None;
+    let mut nchar = None;
+    let mut interleave = false;
+    let mut annotations = HashMap::new();
+    let mut matrix = None;
+    while let Some(keyword) = tokens.next() {
+        let args: Vec<NexusToken> = tokens
+            .by_ref()
+            .take_while(|x| x.quoted || x.text != ";")
+            .collect();
+        match keyword.text.to_ascii_uppercase().as_str() {
+            "BEGIN" => {
+                let name = args.first().map(|x| x.text.to_ascii_uppercase());
+                in_data = matches!(name.as_deref(), Some("DATA" | "CHARACTERS"));
+            }
+            "END" | "ENDBLOCK" if in_data && matrix.is_some() => break,
+            "END" | "ENDBLOCK" => in_data = false,
+            _ if !in_data => {}
+            "DIMENSIONS" => {
+                for (name, value, line) in nexus_options(&args) {
+                    let count = match name.as_str() {
+                        "NTAX" => &mut ntax,
+                        "NCHAR" => &mut nchar,
+                        _ => continue,
+                    };
+                    *count = match value.and_then(|x| x.parse::<usize>().ok()) {
+                        Some(value) => Some(value),
+                        None => {
+                            return Err(Error::ParseError {
+                                line,
+                                message: format!("invalid {} in NEXUS DIMENSIONS", name),
+                            })
+                        }
+                    };
+                }
+            }
+            "FORMAT" => {
+                for (name, value, _) in nexus_options(&args) {
+                    match (name.as_str(), value) {
+                        ("DATATYPE", Some(value)) => {
+                            annotations.insert("datatype".to_string(), value)
+                        }
+                        ("GAP", Some(value)) => annotations.insert("gap".to_string(), value),
+                        ("MISSING", Some(value)) => {
+                            annotations.insert("missing".to_string(), value)
+                        }
+                        ("INTERLEAVE", value) => {
+                            interleave = !matches!(value, Some(x) if x.eq_ignore_ascii_case("NO"));
+                            None
+                        }
+                        _ => None,
+                    };
+                }
+            }
+            "MATRIX" => matrix = Some((keyword.line, args)),
+            _ => {}
         }
+    }
 
-        if buf.starts_with(' ') {
-            msa.add_column_annotation("cons", &buf[start..end]);
+    let (line, matrix) = match matrix {
+        Some(matrix) => matrix,
+        None => {
+            return Err(Error::ParseError {
+                line: input.lines().count(),
+                message: "no MATRIX in a NEXUS DATA or CHARACTERS block".to_string(),
+            })
         }
-        buf.clear();
+    };
+    let nchar = match nchar {
+        Some(nchar) => nchar,
+        None => {
+            return Err(Error::ParseError {
+                line,
+                message: "NEXUS DIMENSIONS do not declare NCHAR".to_string(),
+            })
+        }
+    };
+
+    let mut msa = MSA::new(Vec::new(), annotations, HashMap::new());
+    let mut matrix = matrix.iter().peekable();
+    while let Some(name) = matrix.next() {
+        // Interleaved rows end with their line, others once complete
+        let mut sequence = String::new();
+        while let Some(token) = matrix.peek() {
+            let done = match interleave {
+                true => token.line != name.line || token.quoted,
+                false => sequence.len() >= nchar,
+            };
+            if done {
+                break;
+            }
+            sequence.push_str(&token.text);
+            matrix.next();
+        }
+        msa.push_record(&name.text, &sequence);
+    }
+
+    if let Some(ntax) = ntax.filter(|&x| x != msa.len()) {
+        return Err(Error::ParseError {
+            line,
+            message: format!(
+                "NEXUS DIMENSIONS declare {} taxa but {} were found",
+                ntax,
+                msa.len()
+            ),
+        });
+    }
+    if let Some(record) = msa.records().iter().find(|x| x.len() != nchar) {
+        return Err(Error::UnequalLength {
+            id: record.id().to_string(),
+            expected: nchar,
+            found: record.len(),
+        });
     }
     Ok(msa)
 }
 
-fn read_stockholm<R>(reader: &mut R) -> Result<MSA, Box<dyn Error>>
+/// Parse the `NAME=value` or bare `NAME` options of a NEXUS command into
+/// their uppercase name, value and line
+fn nexus_options(args: &[NexusToken]) -> Vec<(String, Option<String>, usize)> {
+    let mut options = Vec::new();
+    let mut args = args.iter().peekable();
+    while let Some(name) = args.next() {
+        let value = match args.peek() {
+            Some(x) if x.text == "=" && !x.quoted => {
+                args.next();
+                args.next().map(|x| x.text.clone())
+            }
+            _ => None,
+        };
+        options.push((name.text.to_ascii_uppercase(), value, name.line));
+    }
+    options
+}
+
+fn read_stockholm<R>(reader: &mut R) -> Result<MSA, Error>
 where
     R: BufRead,
 {
     let mut msa = MSA::default();
     let reader = Reader::new(reader);
 
+    let mut buf = String::new();
+    reader.inner.read_line(&mut buf)?;
+    if !buf.starts_with("# STOCKHOLM") {
+        return Err(Error::InvalidHeader {
+            found: buf.trim_end().to_string(),
+        });
+    }
+    buf.clear();
+
+    let mut line_no: usize = 1;
+    let mut terminated = false;
+    while reader.inner.read_line(&mut buf)? != 0 {
+        line_no += 1;
+        let line = buf.trim_end();
+
+        if line == "//" {
+            terminated = true;
+            break;
+        } else if let Some(rest) = line.strip_prefix("#=GF") {
+            // Free text tags (like CC) may span several lines
+            let (tag, value) = split_field(rest, line_no)?;
+            let value = match msa.get_annotation(tag) {
+                Some(previous) => format!("{}\n{}", previous, value),
+                None => value.to_string(),
+            };
+            msa.add_annotation(tag.to_string(), value);
+        } else if let Some(rest) = line.strip_prefix("#=GC") {
+            let (tag, value) = split_field(rest, line_no)?;
+            msa.add_column_annotation(tag, value);
+        } else if let Some(rest) = line.strip_prefix("#=GS") {
+            // Per-sequence lines usually precede the sequence itself
+            let (id, rest) = split_field(rest, line_no)?;
+            let (tag, value) = split_field(rest, line_no)?;
+            if !msa.contains(id) {
+                msa.push_record(id, "");
+            }
+            let value = match msa.get_record_annotation(id, tag) {
+                Some(_) => format!("\n{}", value),
+                None => value.to_string(),
+            };
+            msa.push_record_annotation(id, tag, &value);
+        } else if let Some(rest) = line.strip_prefix("#=GR") {
+            let (id, rest) = split_field(rest, line_no)?;
+            let (tag, value) = split_field(rest, line_no)?;
+            if !msa.contains(id) {
+                return Err(Error::ParseError {
+                    line: line_no,
+                    message: format!("#=GR annotation for unknown sequence {}", id),
+                });
+            }
+            msa.push_record_annotation(id, tag, value);
+        } else if !line.starts_with('#') && !line.is_empty() {
+            let (id, sequence) = split_field(line, line_no)?;
+            msa.push_record(id, sequence);
+        }
+        buf.clear();
+    }
+
+    if !terminated {
+        return Err(Error::ParseError {
+            line: line_no,
+            message: "missing // terminator".to_string(),
+        });
+    }
+    msa.validate()?;
     Ok(msa)
 }
 
+/// Split a line into its first whitespace-delimited field and the remainder
+fn split_field(line: &str, line_no: usize) -> Result<(&str, &str), Error> {
+    line.trim_start()
+        .split_once(char::is_whitespace)
+        .map(|(field, rest)| (field, rest.trim()))
+        .ok_or_else(|| Error::ParseError {
+            line: line_no,
+            message: "missing field".to_string(),
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs::File, io::BufReader};
@@ -99,10 +1257,670 @@ mod tests {
         assert_eq!(msa.get_annotation("program").unwrap(), "CLUSTAL");
         assert_eq!(msa.get_annotation("version").unwrap(), "1.81");
         assert_eq!(msa.len(), 2);
+        assert!(matches!(
+            msa.column_track("cons"),
+            Some(ColumnTrack::Chars(_))
+        ));
         let cons = msa.get_column_annotation("cons").unwrap();
         assert_eq!(
             &cons[..50],
             "          * *: ::    :.   :*  :  :. : . :*  ::   ."
         );
     }
+
+    #[test]
+    fn test_clustal_bad_header() {
+        let input = b"NOT A CLUSTAL FILE\n\nseq1   ACGT\n";
+        let err = Reader::new(&input[..]).read_clustal().unwrap_err();
+        assert!(matches!(err, Error::InvalidHeader { found } if found == "NOT A CLUSTAL FILE"));
+    }
+
+    #[test]
+    fn test_clustal_truncated() {
+        let input = std::fs::read_to_string("tests/clustalw.aln").unwrap();
+        let truncated: Vec<&str> = input.lines().take(8).collect();
+        let err = Reader::new(truncated.join("\n").as_bytes())
+            .read_clustal()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnequalLength {
+                expected: 100,
+                found: 50,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_clustal_crlf() {
+        let crlf = Reader::new(BufReader::new(
+            File::open("tests/clustalw_crlf.aln").unwrap(),
+        ))
+        .check_numbering(true)
+        .read_clustal()
+        .unwrap();
+        let lf = Reader::new(BufReader::new(
+            File::open("tests/clustalw_seqnos.aln").unwrap(),
+        ))
+        .read_clustal()
+        .unwrap();
+        assert_eq!(crlf, lf);
+        assert!(!crlf.get_column_annotation("cons").unwrap().contains('\r'));
+    }
+
+    #[test]
+    fn test_clustal_missing_sequence() {
+        let input = b"CLUSTAL W (1.81) multiple sequence alignment\n\nseq1   ACGT\nseq2\n";
+        let err = Reader::new(&input[..]).read_clustal().unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 4, .. }));
+
+        let input = b"CLUSTAL W (1.81) multiple sequence alignment\n\nseq1   ACGT 4\n\n";
+        let msa = Reader::new(&input[..]).read_clustal().unwrap();
+        assert_eq!(msa.records()[0].sequence(), "ACGT");
+    }
+
+    #[test]
+    fn test_clustal_stripped_consensus() {
+        let input = "CLUSTAL W (1.83) multiple sequence alignment\n\n\n\
+            a           ACGTACGT\n\
+            seq_long    ACGAACGA\n\
+            \x20           ***.**\n\n\
+            a        TTGG\n\
+            seq_long TTGC\n\n\n\
+            a        AC\n\
+            seq_long AC\n\
+            \x20        **\n";
+        let msa = Reader::new(input.as_bytes()).read_clustal().unwrap();
+        assert_eq!(msa.col_len(), 14);
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), "***.**      **");
+    }
+
+    #[test]
+    fn test_clustal_ragged_consensus() {
+        let input = "CLUSTAL W (1.83) multiple sequence alignment\n\n\n\
+            AC   ACACAC\n\
+            ACAC ACACAG\n\
+            \x20    *****.\n\n\
+            AC         ACGT\n\
+            ACAC       ACGG\n\
+            \x20          *** \n";
+        let msa = Reader::new(input.as_bytes()).read_clustal().unwrap();
+        assert_eq!(msa.col_len(), 10);
+        assert_eq!(msa.get_column_annotation("cons").unwrap(), "*****.*** ");
+    }
+
+    #[test]
+    fn test_clustal_blocks() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let msa = Reader::new(open("tests/clustalw.aln"))
+            .read_clustal()
+            .unwrap();
+
+        let mut reader = Reader::new(open("tests/clustalw.aln"));
+        let mut blocks = reader.blocks_clustal().unwrap();
+        assert_eq!(blocks.annotations().get("version").unwrap(), "1.81");
+        let mut streamed = MSA::default();
+        let mut cons = String::new();
+        for block in blocks.by_ref() {
+            let block = block.unwrap();
+            assert_eq!(block.len(), msa.len());
+            cons.push_str(block.get_column_annotation("cons").unwrap());
+            for record in &block {
+                streamed.push_record(record.id(), record.sequence());
+            }
+        }
+        assert!(blocks.next().is_none());
+        assert_eq!(streamed.records(), msa.records());
+        assert_eq!(&cons, msa.get_column_annotation("cons").unwrap());
+
+        let input = "CLUSTAL W (1.83)\n\nid1  ACGT\nid2  AC-T\n\nid1  GG\nbroken\n\nid1  CC\n";
+        let mut reader = Reader::new(input.as_bytes());
+        let mut blocks = reader.blocks_clustal().unwrap();
+        assert!(blocks.next().unwrap().is_ok());
+        let err = blocks.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 7, .. }));
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn test_summarize_files() {
+        let rows =
+            summarize_files(&["tests/clustalw.fa", "tests/sorted.fa"], Format::Fasta).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, "tests/clustalw.fa");
+        assert_eq!(rows[0].1.num_rows(), 2);
+        assert_eq!(rows[1].0, "tests/sorted.fa");
+        assert_eq!(rows[1].1.num_rows(), 4);
+        assert_eq!(rows[1].1.num_columns(), 10);
+        assert_eq!(rows[1].1.gap_fraction(), 0.1);
+
+        let err =
+            summarize_files(&["tests/sorted.fa", "tests/clustalw.aln"], Format::Fasta).unwrap_err();
+        assert!(matches!(&err, Error::InFile { path, .. } if path == "tests/clustalw.aln"));
+        assert!(err.to_string().starts_with("tests/clustalw.aln: "));
+    }
+
+    #[test]
+    fn test_clustal_detect_alphabet() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let msa = Reader::new(open("tests/clustalw.aln"))
+            .detect_alphabet(true)
+            .read_clustal()
+            .unwrap();
+        assert_eq!(msa.get_annotation("alphabet").unwrap(), "protein");
+
+        let msa = Reader::new(open("tests/clustalw.aln"))
+            .read_clustal()
+            .unwrap();
+        assert_eq!(msa.get_annotation("alphabet"), None);
+    }
+
+    #[test]
+    fn test_clustal_real_numbering() {
+        for path in ["tests/clustalw.aln", "tests/odd_consensus.aln"] {
+            let mut data =
+                Reader::new(BufReader::new(File::open(path).unwrap())).check_numbering(true);
+            assert!(data.read_clustal().is_ok(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_clustal_seqnos() {
+        let mut data = Reader::new(BufReader::new(
+            File::open("tests/clustalw_seqnos.aln").unwrap(),
+        ))
+        .check_numbering(true);
+        let msa = data.read_clustal().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 26);
+        assert_eq!(&msa.records()[1].sequence()[14..], "AGC--SKEE-PK");
+        assert_eq!(msa.get_column_annotation("cons").unwrap().len(), 26);
+    }
+
+    #[test]
+    fn test_clustal_omega_seqnos() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/clustalo.aln").unwrap()))
+            .check_numbering(true);
+        let msa = data.read_clustal().unwrap();
+        assert_eq!(msa.get_annotation("version").unwrap(), "1.2.4");
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 26);
+        assert_eq!(&msa.records()[0].sequence()[20..], "KEEAPK");
+    }
+
+    #[test]
+    fn test_clustal_seqno_range() {
+        let mut data = Reader::new(BufReader::new(
+            File::open("tests/clustalw_seqno_range.aln").unwrap(),
+        ))
+        .check_numbering(true);
+        let msa = data.read_clustal().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 26);
+        assert_eq!(
+            msa.get_record_annotation("seq2/5-25", "start").unwrap(),
+            "5"
+        );
+        assert_eq!(msa.get_record_annotation("seq2/5-25", "end").unwrap(), "25");
+
+        // Ranges are 1-based, a start of 0 is an error rather than a panic
+        let input = b"CLUSTAL 2.1 multiple sequence alignment\n\n\nseq/0-3   AC-GT 4\n";
+        let err = Reader::new(&input[..])
+            .check_numbering(true)
+            .read_clustal()
+            .unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 4, .. }));
+        let input = b"CLUSTAL 2.1 multiple sequence alignment\n\n\nseq/99999999999999999999999-3   AC-GT 4\n";
+        let err = Reader::new(&input[..])
+            .check_numbering(true)
+            .read_clustal()
+            .unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 4, .. }));
+        assert!(Reader::new(&input[..]).read_clustal().is_ok());
+    }
+
+    #[test]
+    fn test_clustal_lowercase() {
+        let mut data = Reader::new(BufReader::new(
+            File::open("tests/clustalw_lowercase.aln").unwrap(),
+        ));
+        let msa = data.read_clustal().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 26);
+        assert_eq!(msa.records()[0].sequence(), "mkvlaagivalllaagcssskeeapk");
+    }
+
+    #[test]
+    fn test_clustal_bad_numbering() {
+        let input = b"CLUSTAL 2.1 multiple sequence alignment\n\n\nseq1   AC-GT 5\n";
+        let err = Reader::new(&input[..])
+            .check_numbering(true)
+            .read_clustal()
+            .unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 4, .. }));
+    }
+
+    #[test]
+    fn test_stockholm() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/pfam.sto").unwrap()));
+        let msa = data.read_stockholm().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 34);
+
+        // File level
+        assert_eq!(msa.get_annotation("ID").unwrap(), "Ferritin");
+        assert_eq!(
+            msa.get_annotation("CC").unwrap(),
+            "Ferritins are the main intracellular iron storage\n\
+             protein in both prokaryotes and eukaryotes."
+        );
+
+        // Column level
+        let ss_cons = msa.get_column_annotation("SS_cons").unwrap();
+        assert_eq!(ss_cons, ".HHHHHHHH.HHH.HHHHHHH.HHHHHHHHHHHH");
+
+        // Record level
+        assert_eq!(
+            msa.get_record_annotation("DPS_ECOLI/5-150", "AC").unwrap(),
+            "P0ABT2.2"
+        );
+        assert_eq!(
+            msa.get_record_annotation("FRI_LISIN/4-145", "SS").unwrap(),
+            "-HHHHHHHH-HHHhHHHHHHH.HHHHHHHHHHH-"
+        );
+    }
+
+    #[test]
+    fn test_stockholm_terminator_and_repeated_gs() {
+        let input = b"# STOCKHOLM 1.0\n\
+            #=GS seq1 DR PDB; 1ABC\n\
+            #=GS seq1 DR PDB; 2XYZ\n\
+            seq1 AC-GT\n\
+            seq2 ACCGT\n\
+            //\n\
+            seq3 TTTTT\n";
+        let msa = Reader::new(&input[..]).read_stockholm().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert!(!msa.contains("seq3"));
+        assert_eq!(
+            msa.get_record_annotation("seq1", "DR").unwrap(),
+            "PDB; 1ABC\nPDB; 2XYZ"
+        );
+    }
+
+    #[test]
+    fn test_stockholm_invalid() {
+        let read = |input: &[u8]| Reader::new(input).read_stockholm();
+
+        let unequal = b"# STOCKHOLM 1.0\nseq1 ACGT\nseq2 AC\n//\n";
+        assert!(matches!(
+            read(unequal),
+            Err(Error::UnequalLength {
+                expected: 4,
+                found: 2,
+                ..
+            })
+        ));
+
+        let gs_only = b"# STOCKHOLM 1.0\n#=GS seq3 AC P12345\nseq1 ACGT\n//\n";
+        assert!(matches!(
+            read(gs_only),
+            Err(Error::UnequalLength { found: 4, .. })
+        ));
+
+        let unknown_gr = b"# STOCKHOLM 1.0\nseq1 ACGT\n#=GR seq2 SS HHHH\n//\n";
+        assert!(matches!(
+            read(unknown_gr),
+            Err(Error::ParseError { line: 3, .. })
+        ));
+
+        let unterminated = b"# STOCKHOLM 1.0\nseq1 ACGT\nseq2 ACGT\n";
+        assert!(matches!(
+            read(unterminated),
+            Err(Error::ParseError { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_stockholm_bad_header() {
+        let input = b"CLUSTAL W\n";
+        assert!(matches!(
+            Reader::new(&input[..]).read_stockholm(),
+            Err(Error::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn test_nexus() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/primates.nex").unwrap()));
+        let msa = data.read_nexus().unwrap();
+        assert_eq!(msa.len(), 4);
+        assert_eq!(msa.col_len(), 30);
+        assert_eq!(msa.records()[0].id(), "Homo sapiens");
+        assert_eq!(msa.records()[3].id(), "Pongo's cousin");
+        assert_eq!(
+            msa.get_record("Gorilla").unwrap().sequence(),
+            "AAGCTTCACCGGCGCAGTTGTTCTCAC?AC"
+        );
+        assert_eq!(msa.get_annotation("datatype").unwrap(), "DNA");
+        assert_eq!(msa.get_annotation("gap").unwrap(), "-");
+        assert_eq!(msa.get_annotation("missing").unwrap(), "?");
+
+        // Sequential rows may span lines
+        let input = "#nexus\nbegin characters;\n dimensions nchar=6;\n matrix\n a ACG\n TTT\n 'b [x]' AC-[gap]TTT\n;\nend;\n";
+        let msa = Reader::new(input.as_bytes()).read_nexus().unwrap();
+        assert_eq!(msa.get_record("a").unwrap().sequence(), "ACGTTT");
+        assert_eq!(msa.get_record("b [x]").unwrap().sequence(), "AC-TTT");
+        assert_eq!(msa.get_annotation("datatype"), None);
+    }
+
+    #[test]
+    fn test_nexus_errors() {
+        let read = |input: &str| Reader::new(input.as_bytes()).read_nexus();
+        assert!(matches!(
+            read("BEGIN DATA;\n"),
+            Err(Error::InvalidHeader { .. })
+        ));
+        assert!(matches!(
+            read("#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=3 NCHAR=2;\nMATRIX\na AC\nb AC\n;\nEND;\n"),
+            Err(Error::ParseError { line: 4, .. })
+        ));
+        assert!(matches!(
+            read("#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=3;\nFORMAT INTERLEAVE;\nMATRIX\na AC\nb ACG\n;\nEND;\n"),
+            Err(Error::UnequalLength { expected: 3, found: 2, .. })
+        ));
+        assert!(matches!(
+            read("#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=x;\n"),
+            Err(Error::ParseError { line: 3, .. })
+        ));
+        assert!(matches!(
+            read("#NEXUS\n[ open\nBEGIN DATA;\n"),
+            Err(Error::ParseError { line: 2, .. })
+        ));
+        assert!(matches!(
+            read("#NEXUS\nBEGIN TREES;\nEND;\n"),
+            Err(Error::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_path_gzip() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let dir = std::env::temp_dir();
+        let compressed = dir.join(format!("align-rs-{}.aln.gz", std::process::id()));
+        let mut encoder =
+            GzEncoder::new(File::create(&compressed).unwrap(), Compression::default());
+        encoder
+            .write_all(&std::fs::read("tests/clustalw.aln").unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+        let msa = Reader::from_path(&compressed)
+            .unwrap()
+            .read_clustal()
+            .unwrap();
+        let expected = Reader::from_path("tests/clustalw.aln")
+            .unwrap()
+            .read_clustal()
+            .unwrap();
+        assert_eq!(msa, expected);
+
+        // A gzip header followed by garbage
+        let garbage = dir.join(format!("align-rs-{}.garbage", std::process::id()));
+        std::fs::write(
+            &garbage,
+            [0x1f, 0x8b, 0x08, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00],
+        )
+        .unwrap();
+        let result = Reader::from_path(&garbage).unwrap().read_clustal();
+        assert!(matches!(result, Err(Error::Io(_))));
+
+        std::fs::remove_file(compressed).unwrap();
+        std::fs::remove_file(garbage).unwrap();
+        assert!(matches!(
+            Reader::from_path(dir.join("missing.aln")),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_phylip() {
+        for path in [
+            "tests/strict_sequential.phy",
+            "tests/strict_interleaved.phy",
+            "tests/relaxed.phy",
+        ] {
+            let mut data = Reader::new(BufReader::new(File::open(path).unwrap()));
+            let msa = data.read_phylip().unwrap();
+            assert_eq!(msa.len(), 4, "{}", path);
+            assert_eq!(msa.col_len(), 30, "{}", path);
+            assert_eq!(
+                &msa.records()[1].sequence()[..20],
+                "CCAAGCTTGAGCAATCAACA",
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_phylip_relaxed_names() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/relaxed.phy").unwrap()));
+        let msa = data.read_phylip().unwrap();
+        assert!(msa.contains("Mus_musculus_castaneus"));
+        assert_eq!(&msa.records()[3].sequence()[20..], "AGCC-CTCGT");
+    }
+
+    #[test]
+    fn test_phylip_strict_names() {
+        let mut data = Reader::new(BufReader::new(
+            File::open("tests/strict_names.phy").unwrap(),
+        ));
+        let msa = data.read_phylip().unwrap();
+        assert_eq!(msa.len(), 3);
+        assert_eq!(msa.col_len(), 20);
+        assert!(msa.contains("Homo sapie"));
+        assert_eq!(msa.records()[2].id(), "Gorilla001");
+        assert_eq!(msa.records()[2].sequence(), "AAGCTTCACCGGCGCAGTTG");
+    }
+
+    #[test]
+    fn test_phylip_dimension_mismatch() {
+        let input = b"3 4\nseq1 ACGT\nseq2 ACGT\n";
+        let err = Reader::new(&input[..]).read_phylip().unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 1, .. }));
+
+        let input = b"2 5\nseq1 ACGT\nseq2 ACGT\n";
+        assert!(matches!(
+            Reader::new(&input[..]).read_phylip(),
+            Err(Error::UnequalLength {
+                expected: 5,
+                found: 4,
+                ..
+            })
+        ));
+
+        let input = b"0 4\nseq1 ACGT\n";
+        let err = Reader::new(&input[..]).read_phylip().unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_fasta() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/clustalw.fa").unwrap()));
+        let msa = data.read_fasta().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 601);
+        assert!(msa.contains("gi|671626|emb|CAA85685.1|"));
+    }
+
+    #[test]
+    fn test_fasta_records() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let msa = Reader::new(open("tests/clustalw.fa")).read_fasta().unwrap();
+        let mut reader = Reader::new(open("tests/clustalw.fa"));
+        let records: Vec<Record> = reader.records_fasta().map(|x| x.unwrap()).collect();
+        assert_eq!(records, msa.records());
+
+        let input = b">id1\nACGT\n>\nAC\n>id3\nAC\n";
+        let mut reader = Reader::new(&input[..]);
+        let mut records = reader.records_fasta();
+        assert_eq!(records.next().unwrap().unwrap().sequence(), "ACGT");
+        let err = records.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::ParseError { line: 3, .. }));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_fasta_records_io_error_and_eof() {
+        // Invalid UTF-8 fails the read of its line
+        let input = b">id1\nAC\n>id2\n\xff\xfe\n>id3\nAC\n";
+        let mut reader = Reader::new(&input[..]);
+        let mut records = reader.records_fasta();
+        assert_eq!(records.next().unwrap().unwrap().id(), "id1");
+        assert!(matches!(records.next(), Some(Err(Error::Io(_)))));
+        assert!(records.next().is_none());
+
+        let mut reader = Reader::new(&b">id1\nAC"[..]);
+        let mut records = reader.records_fasta();
+        assert_eq!(records.next().unwrap().unwrap().sequence(), "AC");
+        assert!(records.next().is_none());
+        assert!(records.next().is_none());
+        assert_eq!(Reader::new(&b""[..]).records_fasta().count(), 0);
+    }
+
+    #[test]
+    fn test_a3m() {
+        let open = |path: &str| BufReader::new(File::open(path).unwrap());
+        let msa = Reader::new(open("tests/hhblits.a3m"))
+            .read_a3m(InsertHandling::Strip)
+            .unwrap();
+        assert_eq!(msa.len(), 4);
+        assert_eq!(msa.col_len(), 19);
+        assert_eq!(msa.get(1).unwrap().sequence(), "MGLSDGEWQLVLNVWVEAD");
+        assert_eq!(msa.get(2).unwrap().sequence(), "----ADFDVLKCWGPVEAD");
+        assert_eq!(
+            msa.get_record_annotation("tr|A0A2K5|A0A2K5_MOUSE", "a3m")
+                .unwrap(),
+            "MGLSDGEWQLVLNVWgkVEAD"
+        );
+        assert_eq!(
+            msa.get_record_annotation("101m_A", "description").unwrap(),
+            "mol:protein length:19  MYOGLOBIN"
+        );
+
+        let msa = Reader::new(open("tests/hhblits.a3m"))
+            .read_a3m(InsertHandling::Pad)
+            .unwrap();
+        assert_eq!(msa.col_len(), 26);
+        msa.validate().unwrap();
+        let sequences: Vec<&str> = msa.iter().map(|x| x.sequence()).collect();
+        assert_eq!(
+            sequences,
+            vec![
+                "MVLSEGEW.QLVLHVW..AKVE....",
+                "MGLSDGEW.QLVLNVWgkVEAD....",
+                "----ADFDaVLKCWGP..VEAD....",
+                "MVLSEGEW.QLVLHVW..AKVEdvag",
+            ]
+        );
+        assert_eq!(msa.get_record_annotation("101m_A", "a3m"), None);
+
+        let input = b">id1\nACgtGT\n>id2\nAC\n";
+        let err = Reader::new(&input[..])
+            .read_a3m(InsertHandling::Pad)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnequalLength {
+                expected: 4,
+                found: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_maf_blocks() {
+        let mut reader = Reader::new(BufReader::new(File::open("tests/ucsc.maf").unwrap()));
+        let blocks: Vec<MSA> = reader.read_maf_blocks().map(|x| x.unwrap()).collect();
+        assert_eq!(blocks.len(), 2);
+
+        assert_eq!(blocks[0].get_annotation("score").unwrap(), "23262.0");
+        assert_eq!(blocks[0].len(), 5);
+        assert_eq!(blocks[0].col_len(), 42);
+        assert_eq!(blocks[0].get(4).unwrap().id(), "rn3.chr4");
+        assert_eq!(
+            blocks[0].get_record_annotation("rn3.chr4", "size").unwrap(),
+            "40"
+        );
+
+        let rat = blocks[1].get(4).unwrap();
+        assert_eq!(rat.sequence(), "taagga");
+        assert_eq!(rat.get_annotation("start").unwrap(), "81444246");
+        assert_eq!(rat.get_annotation("strand").unwrap(), "-");
+        assert_eq!(rat.get_annotation("srcSize").unwrap(), "187371129");
+        assert_eq!(blocks[1].len(), 5);
+
+        // Blocks need not be separated by blank lines
+        let input = "a score=1\ns a 0 2 + 9 AC\na score=2\ns a 2 2 + 9 GT\ns b 0 1 + 9 G-\n";
+        let mut reader = Reader::new(input.as_bytes());
+        let blocks: Vec<MSA> = reader.read_maf_blocks().map(|x| x.unwrap()).collect();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].get_annotation("score").unwrap(), "2");
+        assert_eq!(blocks[1].len(), 2);
+
+        let input = "a score=1\ns a 0 2 + 9\n";
+        let mut reader = Reader::new(input.as_bytes());
+        let mut blocks = reader.read_maf_blocks();
+        assert!(matches!(
+            blocks.next(),
+            Some(Err(Error::ParseError { line: 2, .. }))
+        ));
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn test_fasta_description() {
+        let input = b">seq1 first sequence\nAC-G\nTT\n>seq2\nACGG\n-T\n";
+        let msa = Reader::new(&input[..]).read_fasta().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.col_len(), 6);
+        assert_eq!(
+            msa.get_record_annotation("seq1", "description").unwrap(),
+            "first sequence"
+        );
+        assert_eq!(msa.get_record_annotation("seq2", "description"), None);
+    }
+
+    #[test]
+    fn test_fasta_wrapped_blank_lines_no_final_newline() {
+        let input = b">seq1\nAC\nGT\nAC\n\n\n>seq2 desc\nA-\n\nGTTC";
+        let msa = Reader::new(&input[..]).read_fasta().unwrap();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.records()[0].sequence(), "ACGTAC");
+        assert_eq!(msa.records()[1].sequence(), "A-GTTC");
+        assert_eq!(
+            msa.get_record_annotation("seq2", "description").unwrap(),
+            "desc"
+        );
+    }
+
+    #[test]
+    fn test_fasta_sequence_before_header() {
+        let input = b"ACGT\n>seq1\nACGT\n";
+        assert!(matches!(
+            Reader::new(&input[..]).read_fasta(),
+            Err(Error::ParseError { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_fasta_unequal_length() {
+        let input = b">seq1\nACGT\n>seq2\nACG\n";
+        let err = Reader::new(&input[..]).read_fasta().unwrap_err();
+        assert!(matches!(err, Error::UnequalLength { id, expected: 4, found: 3 } if id == "seq2"));
+    }
 }
@@ -1,8 +1,13 @@
 use std::collections::HashMap;
 
+use crate::codon::CodonTable;
+use crate::error::Error;
+use crate::msa::GapSet;
+
 /// Simple Sequence Record Structure for multiple sequence alignment
 ///
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Record {
     /// Sequence ID
     id: String,
@@ -36,6 +41,232 @@ impl Record {
         &self.sequence
     }
 
+    pub(crate) fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotation
+    }
+
+    /// Returns a copy of this record with its sequence replaced by `sequence`
+    pub(crate) fn with_sequence(&self, sequence: String) -> Record {
+        Record {
+            id: self.id.clone(),
+            sequence,
+            annotation: self.annotation.clone(),
+        }
+    }
+
+    /// Returns the sequence with every gap (`-` or `.`) removed
+    pub fn ungapped(&self) -> String {
+        self.ungapped_with(&GapSet::default())
+    }
+
+    /// Returns the sequence with every character of `gaps` removed
+    pub fn ungapped_with(&self, gaps: &GapSet) -> String {
+        self.sequence
+            .chars()
+            .filter(|&c| !gaps.contains(c))
+            .collect()
+    }
+
+    /// Returns a copy of the record, annotations included, with the gaps
+    /// removed from its sequence. The result is usually shorter than the
+    /// other records of its alignment.
+    /// # Example
+    /// ```
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "-AC.G-");
+    /// assert_eq!(record.ungap(), Record::new("id1", "ACG"));
+    /// ```
+    pub fn ungap(&self) -> Record {
+        self.with_sequence(self.ungapped())
+    }
+
+    /// Returns a copy of the record like [`Record::ungap`], removing the
+    /// characters of `gaps` from its sequence
+    /// # Example
+    /// ```
+    /// use align_rs::msa::GapSet;
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "~AC.G-");
+    /// assert_eq!(record.ungap_with(&GapSet::new(&['-', '~'])).sequence(), "AC.G");
+    /// ```
+    pub fn ungap_with(&self, gaps: &GapSet) -> Record {
+        self.with_sequence(self.ungapped_with(gaps))
+    }
+
+    /// Returns the alignment column holding residue `pos` (0-based, gaps
+    /// not counted), or `None` if the sequence has fewer residues
+    /// # Example
+    /// ```
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "-AC.G");
+    /// assert_eq!(record.seq_pos_to_column(2), Some(4));
+    /// assert_eq!(record.column_to_seq_pos(3), None);
+    /// ```
+    pub fn seq_pos_to_column(&self, pos: usize) -> Option<usize> {
+        self.seq_pos_to_column_with(pos, &GapSet::default())
+    }
+
+    /// Returns the alignment column holding residue `pos` like
+    /// [`Record::seq_pos_to_column`], the characters of `gaps` not counted
+    pub fn seq_pos_to_column_with(&self, pos: usize, gaps: &GapSet) -> Option<usize> {
+        self.sequence
+            .bytes()
+            .enumerate()
+            .filter(|(_, b)| !gaps.is_gap(b))
+            .nth(pos)
+            .map(|(col, _)| col)
+    }
+
+    /// Returns the residue position (0-based, gaps not counted) at alignment
+    /// column `col`, or `None` if the column is a gap or out of range
+    pub fn column_to_seq_pos(&self, col: usize) -> Option<usize> {
+        self.column_to_seq_pos_with(col, &GapSet::default())
+    }
+
+    /// Returns the residue position at column `col` like
+    /// [`Record::column_to_seq_pos`], the characters of `gaps` being gaps
+    /// # Example
+    /// ```
+    /// use align_rs::msa::GapSet;
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "~AC.G");
+    /// let gaps = GapSet::new(&['~']);
+    /// assert_eq!(record.column_to_seq_pos_with(4, &gaps), Some(3));
+    /// assert_eq!(record.seq_pos_to_column_with(0, &gaps), Some(1));
+    /// ```
+    pub fn column_to_seq_pos_with(&self, col: usize, gaps: &GapSet) -> Option<usize> {
+        let bytes = self.sequence.as_bytes();
+        if gaps.is_gap(bytes.get(col)?) {
+            return None;
+        }
+        Some(bytes[..col].iter().filter(|b| !gaps.is_gap(b)).count())
+    }
+
+    /// Translates the sequence codon by codon from its first column with
+    /// `table`, writing `-` for gap codons and `X` for codons holding gaps
+    /// or ambiguous bases. An incomplete trailing codon is dropped. The
+    /// letter annotations, which describe nucleotides, are not carried over.
+    /// # Example
+    /// ```
+    /// use align_rs::codon::CodonTable;
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "ATG---AARTAAG");
+    /// let table = CodonTable::from_ncbi(1).unwrap();
+    /// assert_eq!(record.translate(&table).sequence(), "M-X*");
+    /// ```
+    pub fn translate(&self, table: &CodonTable) -> Record {
+        self.translate_with(table, &GapSet::default())
+    }
+
+    /// Translates the sequence like [`Record::translate`], codons made only
+    /// of characters of `gaps` becoming `-`
+    pub fn translate_with(&self, table: &CodonTable, gaps: &GapSet) -> Record {
+        let protein: String = self
+            .sequence
+            .as_bytes()
+            .chunks_exact(3)
+            .map(|codon| table.translate_with(codon, gaps) as char)
+            .collect();
+        Record::new(&self.id, &protein)
+    }
+
+    /// Returns the reverse complement of the record, keeping its ID. IUPAC
+    /// ambiguity codes are complemented, case and gaps (`-` and `.`) are
+    /// preserved. `A` pairs with `U` if the sequence holds a `U` and no `T`.
+    /// Letter annotations as long as the sequence are reversed, the others
+    /// are copied. Fails on the first character that is not a nucleotide
+    /// code, such as in protein sequences.
+    /// # Example
+    /// ```
+    /// use align_rs::record::Record;
+    ///
+    /// let record = Record::new("id1", "AcG-TN");
+    /// assert_eq!(record.reverse_complement().unwrap().sequence(), "NA-CgT");
+    /// assert!(Record::new("id2", "MKLV").reverse_complement().is_err());
+    /// ```
+    pub fn reverse_complement(&self) -> Result<Record, Error> {
+        let rna = self.sequence.contains(['U', 'u']) && !self.sequence.contains(['T', 't']);
+        let complement = |c: char| -> Option<char> {
+            let upper = match c.to_ascii_uppercase() {
+                '-' | '.' => return Some(c),
+                'A' if rna => 'U',
+                'A' => 'T',
+                'T' | 'U' => 'A',
+                'G' => 'C',
+                'C' => 'G',
+                'R' => 'Y',
+                'Y' => 'R',
+                'K' => 'M',
+                'M' => 'K',
+                'B' => 'V',
+                'V' => 'B',
+                'D' => 'H',
+                'H' => 'D',
+                'S' | 'W' | 'N' => c.to_ascii_uppercase(),
+                _ => return None,
+            };
+            if c.is_ascii_lowercase() {
+                Some(upper.to_ascii_lowercase())
+            } else {
+                Some(upper)
+            }
+        };
+        let mut sequence = String::with_capacity(self.sequence.len());
+        for (column, c) in self.sequence.chars().enumerate() {
+            match complement(c) {
+                Some(c) => sequence.push(c),
+                None => {
+                    return Err(Error::InvalidResidue {
+                        id: self.id.clone(),
+                        column,
+                        residue: c,
+                    })
+                }
+            }
+        }
+        let sequence: String = sequence.chars().rev().collect();
+        let len = sequence.chars().count();
+        Ok(Record {
+            id: self.id.clone(),
+            sequence,
+            annotation: self
+                .annotation
+                .iter()
+                .map(|(k, v)| match v.chars().count() == len {
+                    true => (k.clone(), v.chars().rev().collect()),
+                    false => (k.clone(), v.clone()),
+                })
+                .collect(),
+        })
+    }
+
+    /// Converts the sequence to uppercase in place. Annotations are left
+    /// untouched.
+    pub fn to_uppercase(&mut self) {
+        self.sequence.make_ascii_uppercase();
+    }
+
+    /// Converts the sequence to lowercase in place. Annotations are left
+    /// untouched.
+    pub fn to_lowercase(&mut self) {
+        self.sequence.make_ascii_lowercase();
+    }
+
+    /// Rewrites every character of `gaps` in the sequence as `gap_char`.
+    /// Annotations are left untouched.
+    pub fn normalize_gaps(&mut self, gaps: &GapSet, gap_char: char) {
+        self.sequence = self
+            .sequence
+            .chars()
+            .map(|c| if gaps.contains(c) { gap_char } else { c })
+            .collect();
+    }
+
     /// Append a sequence string to the existing string
     pub fn push_seq(&mut self, string: &str) {
         self.sequence.push_str(string);
@@ -56,6 +287,11 @@ impl Record {
         }
     }
 
+    /// Returns the letter annotation stored under `name`, if any
+    pub fn get_annotation(&self, name: &str) -> Option<&String> {
+        self.annotation.get(name)
+    }
+
     pub fn push_annotation(&mut self, name: &str, value: &str) {
         if let Some(val) = self.annotation.get_mut(name) {
             val.push_str(value);
@@ -64,3 +300,91 @@ impl Record {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_translate() {
+        let record = Record::new("id1", "atgAGA-..NNNTGATT");
+        let standard = record.translate(&CodonTable::standard());
+        assert_eq!(standard.id(), "id1");
+        assert_eq!(standard.sequence(), "MR-X*");
+        let mitochondrial = record.translate(&CodonTable::from_ncbi(2).unwrap());
+        assert_eq!(mitochondrial.sequence(), "M*-XW");
+        assert!(Record::new("id2", "AT")
+            .translate(&CodonTable::standard())
+            .sequence()
+            .is_empty());
+    }
+
+    #[test]
+    fn record_reverse_complement() {
+        let mut record = Record::new("id1", "ACGTRYKMSWBDHVN-.s");
+        record.push_annotation("SS", "HHHHHHEEEEEE......");
+        record.push_annotation("start", "12");
+        let reversed = record.reverse_complement().unwrap();
+        assert_eq!(reversed.id(), "id1");
+        assert_eq!(reversed.sequence(), "s.-NBDHVWSKMRYACGT");
+        assert_eq!(reversed.get_annotation("SS").unwrap(), "......EEEEEEHHHHHH");
+        assert_eq!(reversed.get_annotation("start").unwrap(), "12");
+        assert_eq!(reversed.reverse_complement().unwrap(), record);
+
+        let rna = Record::new("id2", "aUGc");
+        assert_eq!(rna.reverse_complement().unwrap().sequence(), "gCAu");
+
+        let gapped = Record::new("id3", "ac--Gt.a");
+        assert_eq!(gapped.reverse_complement().unwrap().sequence(), "t.aC--gt");
+
+        let protein = Record::new("id4", "MKV-LE");
+        assert!(matches!(
+            protein.reverse_complement(),
+            Err(Error::InvalidResidue {
+                column: 4,
+                residue: 'L',
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn record_case() {
+        let mut record = Record::new("id1", "acGT-n");
+        record.push_annotation("mask", "xxXX.x");
+        record.to_uppercase();
+        assert_eq!(record.sequence(), "ACGT-N");
+        assert_eq!(record.get_annotation("mask").unwrap(), "xxXX.x");
+        record.to_lowercase();
+        assert_eq!(record.sequence(), "acgt-n");
+        assert_eq!(record.id(), "id1");
+        record.normalize_gaps(&GapSet::from(['-', '~']), '.');
+        assert_eq!(record.sequence(), "acgt.n");
+        assert_eq!(record.get_annotation("mask").unwrap(), "xxXX.x");
+    }
+
+    #[test]
+    fn record_coordinate_mapping() {
+        let mut record = Record::new("id1", "--AC-G.T..");
+        record.push_annotation("description", "test");
+        assert_eq!(record.ungapped(), "ACGT");
+        let ungapped = record.ungap();
+        assert_eq!(ungapped.id(), "id1");
+        assert_eq!(ungapped.sequence(), "ACGT");
+        assert_eq!(ungapped.get_annotation("description").unwrap(), "test");
+
+        let columns: Vec<_> = (0..5).map(|pos| record.seq_pos_to_column(pos)).collect();
+        assert_eq!(columns, vec![Some(2), Some(3), Some(5), Some(7), None]);
+
+        assert_eq!(record.column_to_seq_pos(0), None);
+        assert_eq!(record.column_to_seq_pos(2), Some(0));
+        assert_eq!(record.column_to_seq_pos(7), Some(3));
+        assert_eq!(record.column_to_seq_pos(9), None);
+        assert_eq!(record.column_to_seq_pos(10), None);
+
+        for pos in 0..4 {
+            let col = record.seq_pos_to_column(pos).unwrap();
+            assert_eq!(record.column_to_seq_pos(col), Some(pos));
+        }
+    }
+}
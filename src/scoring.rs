@@ -0,0 +1,114 @@
+/// Residue order of the built-in substitution tables
+const BLOSUM62_ALPHABET: &[u8; 24] = b"ARNDCQEGHILKMFPSTWYVBZX*";
+
+#[rustfmt::skip]
+const BLOSUM62: [[i32; 24]; 24] = [
+    [ 4, -1, -2, -2,  0, -1, -1,  0, -2, -1, -1, -1, -1, -2, -1,  1,  0, -3, -2,  0, -2, -1,  0, -4],
+    [-1,  5,  0, -2, -3,  1,  0, -2,  0, -3, -2,  2, -1, -3, -2, -1, -1, -3, -2, -3, -1,  0, -1, -4],
+    [-2,  0,  6,  1, -3,  0,  0,  0,  1, -3, -3,  0, -2, -3, -2,  1,  0, -4, -2, -3,  3,  0, -1, -4],
+    [-2, -2,  1,  6, -3,  0,  2, -1, -1, -3, -4, -1, -3, -3, -1,  0, -1, -4, -3, -3,  4,  1, -1, -4],
+    [ 0, -3, -3, -3,  9, -3, -4, -3, -3, -1, -1, -3, -1, -2, -3, -1, -1, -2, -2, -1, -3, -3, -2, -4],
+    [-1,  1,  0,  0, -3,  5,  2, -2,  0, -3, -2,  1,  0, -3, -1,  0, -1, -2, -1, -2,  0,  3, -1, -4],
+    [-1,  0,  0,  2, -4,  2,  5, -2,  0, -3, -3,  1, -2, -3, -1,  0, -1, -3, -2, -2,  1,  4, -1, -4],
+    [ 0, -2,  0, -1, -3, -2, -2,  6, -2, -4, -4, -2, -3, -3, -2,  0, -2, -2, -3, -3, -1, -2, -1, -4],
+    [-2,  0,  1, -1, -3,  0,  0, -2,  8, -3, -3, -1, -2, -1, -2, -1, -2, -2,  2, -3,  0,  0, -1, -4],
+    [-1, -3, -3, -3, -1, -3, -3, -4, -3,  4,  2, -3,  1,  0, -3, -2, -1, -3, -1,  3, -3, -3, -1, -4],
+    [-1, -2, -3, -4, -1, -2, -3, -4, -3,  2,  4, -2,  2,  0, -3, -2, -1, -2, -1,  1, -4, -3, -1, -4],
+    [-1,  2,  0, -1, -3,  1,  1, -2, -1, -3, -2,  5, -1, -3, -1,  0, -1, -3, -2, -2,  0,  1, -1, -4],
+    [-1, -1, -2, -3, -1,  0, -2, -3, -2,  1,  2, -1,  5,  0, -2, -1, -1, -1, -1,  1, -3, -1, -1, -4],
+    [-2, -3, -3, -3, -2, -3, -3, -3, -1,  0,  0, -3,  0,  6, -4, -2, -2,  1,  3, -1, -3, -3, -1, -4],
+    [-1, -2, -2, -1, -3, -1, -1, -2, -2, -3, -3, -1, -2, -4,  7, -1, -1, -4, -3, -2, -2, -1, -2, -4],
+    [ 1, -1,  1,  0, -1,  0,  0,  0, -1, -2, -2,  0, -1, -2, -1,  4,  1, -3, -2, -2,  0,  0,  0, -4],
+    [ 0, -1,  0, -1, -1, -1, -1, -2, -2, -1, -1, -1, -1, -2, -1,  1,  5, -2, -2,  0, -1, -1,  0, -4],
+    [-3, -3, -4, -4, -2, -2, -3, -2, -2, -3, -2, -3, -1,  1, -4, -3, -2, 11,  2, -3, -4, -3, -2, -4],
+    [-2, -2, -2, -3, -2, -1, -2, -3,  2, -1, -1, -2, -1,  3, -3, -2, -2,  2,  7, -1, -3, -2, -1, -4],
+    [ 0, -3, -3, -3, -1, -2, -2, -3, -3,  3,  1, -2,  1, -1, -2, -2,  0, -3, -1,  4, -3, -2, -1, -4],
+    [-2, -1,  3,  4, -3,  0,  1, -1,  0, -3, -4,  0, -3, -3, -2,  0, -1, -4, -3, -3,  4,  1, -1, -4],
+    [-1,  0,  0,  1, -3,  3,  4, -2,  0, -3, -3,  1, -1, -3, -1,  0, -1, -3, -2, -2,  1,  4, -1, -4],
+    [ 0, -1, -1, -1, -2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -2,  0,  0, -2, -1, -1, -1, -1, -1, -4],
+    [-4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4,  1],
+];
+
+/// Substitution scores between residues, plus a linear gap penalty,
+/// used by the pairwise aligner and the substitution-aware statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringMatrix {
+    /// Score of every pair of ASCII characters, row-major
+    scores: Vec<i32>,
+
+    /// Score of aligning a residue against a gap
+    gap: i32,
+}
+
+impl ScoringMatrix {
+    /// Creates a matrix scoring `match_score` for identical residues (case
+    /// insensitive) and `mismatch` otherwise
+    pub fn new(match_score: i32, mismatch: i32, gap: i32) -> Self {
+        let mut scores = vec![mismatch; 128 * 128];
+        for a in 0..128u8 {
+            for b in 0..128u8 {
+                if a.eq_ignore_ascii_case(&b) {
+                    scores[a as usize * 128 + b as usize] = match_score;
+                }
+            }
+        }
+        ScoringMatrix { scores, gap }
+    }
+
+    /// Creates the BLOSUM62 amino-acid substitution matrix. Characters
+    /// outside the matrix alphabet are scored as `X`.
+    pub fn blosum62(gap: i32) -> Self {
+        let index = |c: u8| {
+            BLOSUM62_ALPHABET
+                .iter()
+                .position(|&x| x == c.to_ascii_uppercase())
+                .unwrap_or(22)
+        };
+        let mut scores = vec![0; 128 * 128];
+        for a in 0..128u8 {
+            for b in 0..128u8 {
+                scores[a as usize * 128 + b as usize] = BLOSUM62[index(a)][index(b)];
+            }
+        }
+        ScoringMatrix { scores, gap }
+    }
+
+    /// Returns the substitution score of residues `a` and `b`. Non-ASCII
+    /// bytes are scored like `X`.
+    pub fn score(&self, a: u8, b: u8) -> i32 {
+        let a = if a.is_ascii() { a } else { b'X' };
+        let b = if b.is_ascii() { b } else { b'X' };
+        self.scores[a as usize * 128 + b as usize]
+    }
+
+    /// Returns the score of aligning a residue against a gap
+    pub fn gap(&self) -> i32 {
+        self.gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blosum62_symmetric() {
+        for (i, row) in BLOSUM62.iter().enumerate() {
+            for (j, &score) in row.iter().enumerate() {
+                assert_eq!(score, BLOSUM62[j][i]);
+            }
+        }
+        let matrix = ScoringMatrix::blosum62(-4);
+        assert_eq!(matrix.score(b'W', b'W'), 11);
+        assert_eq!(matrix.score(b'i', b'V'), 3);
+        assert_eq!(matrix.score(b'J', b'A'), 0);
+    }
+
+    #[test]
+    fn test_identity_matrix() {
+        let matrix = ScoringMatrix::new(2, -1, -2);
+        assert_eq!(matrix.score(b'a', b'A'), 2);
+        assert_eq!(matrix.score(b'A', b'C'), -1);
+        assert_eq!(matrix.gap(), -2);
+    }
+}
@@ -0,0 +1,191 @@
+//! Structured instrumentation of the major operations.
+//!
+//! Everything in here compiles down to nothing unless the `tracing` feature
+//! is enabled, in which case spans and events are emitted through the
+//! [`tracing`](https://docs.rs/tracing) facade.
+
+use crate::msa::MSA;
+
+/// Emit a debug-level event when the `tracing` feature is enabled
+#[cfg(feature = "tracing")]
+macro_rules! debug {
+    ($($arg:tt)*) => { ::tracing::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {};
+}
+
+pub(crate) use debug;
+
+/// A running operation: an `align_rs` span entered for its duration, closed
+/// by an info-level event carrying the output dimensions and elapsed time.
+pub(crate) struct Operation {
+    #[cfg(feature = "tracing")]
+    name: &'static str,
+    #[cfg(feature = "tracing")]
+    start: std::time::Instant,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl Operation {
+    #[inline]
+    pub(crate) fn start(_name: &'static str) -> Self {
+        Operation {
+            #[cfg(feature = "tracing")]
+            name: _name,
+            #[cfg(feature = "tracing")]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "tracing")]
+            _span: tracing::info_span!("align_rs", operation = _name).entered(),
+        }
+    }
+
+    /// Report the end of the operation with the dimensions of `msa`
+    #[inline]
+    pub(crate) fn finish(self, msa: &MSA) {
+        self.finish_with(msa.len(), msa.col_len());
+    }
+
+    /// Report the end of an operation whose output is not an alignment,
+    /// with `_rows` sequences of `_cols` columns
+    #[inline]
+    pub(crate) fn finish_with(self, _rows: usize, _cols: usize) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            operation = self.name,
+            rows = _rows,
+            cols = _cols,
+            elapsed_us = self.start.elapsed().as_micros() as u64,
+            "operation finished"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::msa::MSA;
+    use crate::pairwise::global_align;
+    use crate::reader::Reader;
+    use crate::scoring::ScoringMatrix;
+
+    /// Fields of one captured event, values rendered with `Debug`
+    pub(crate) type Fields = HashMap<String, String>;
+
+    struct FieldVisitor<'a>(&'a mut Fields);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    /// Minimal subscriber storing every event it sees
+    #[derive(Clone, Default)]
+    pub(crate) struct Capture(Arc<Mutex<Vec<Fields>>>);
+
+    impl Capture {
+        pub(crate) fn events(&self) -> Vec<Fields> {
+            self.0.lock().unwrap().clone()
+        }
+
+        /// Captured events whose `operation` field is `name`
+        pub(crate) fn operation(&self, name: &str) -> Vec<Fields> {
+            let name = format!("{:?}", name);
+            self.events()
+                .into_iter()
+                .filter(|x| x.get("operation") == Some(&name))
+                .collect()
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::new();
+            event.record(&mut FieldVisitor(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_reader_events() {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let input = b">seq1\nACGT\n>seq2\nAC-T\n";
+            Reader::new(&input[..]).read_fasta().unwrap();
+        });
+
+        let events = capture.operation("read_fasta");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["rows"], "2");
+        assert_eq!(events[0]["cols"], "4");
+        assert!(events[0].contains_key("elapsed_us"));
+    }
+
+    /// Captured events whose message is `message`
+    fn messages(capture: &Capture, message: &str) -> Vec<Fields> {
+        capture
+            .events()
+            .into_iter()
+            .filter(|x| x.get("message").map(String::as_str) == Some(message))
+            .collect()
+    }
+
+    #[test]
+    fn test_msa_events() {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let mut msa = MSA::default();
+            msa.push_record("id1", "AC-GT");
+            msa.push_record("id2", "AC-GT");
+            msa.push_record("id3", "TC-GA");
+            msa.dedupe(1.0);
+            msa.trim_to_reference("id1").unwrap();
+            msa.minimum_spanning_tree(true);
+            global_align(b"ACGT", b"AGT", &ScoringMatrix::new(1, -1, -1));
+        });
+
+        let dedupe = capture.operation("dedupe");
+        assert_eq!(dedupe[0]["rows"], "2");
+        let collapsed = messages(&capture, "collapsed duplicate records");
+        assert_eq!(collapsed[0]["removed"], "1");
+
+        let trimmed = capture.operation("trim_to_reference");
+        assert_eq!(trimmed[0]["rows"], "2");
+        assert_eq!(trimmed[0]["cols"], "4");
+        let removed = messages(&capture, "removed reference gap columns");
+        assert_eq!(removed[0]["removed_columns"], "1");
+
+        let tree = capture.operation("minimum_spanning_tree");
+        assert_eq!(tree[0]["rows"], "2");
+        assert_eq!(
+            messages(&capture, "built minimum spanning tree")[0]["edges"],
+            "1"
+        );
+
+        let aligned = capture.operation("global_align");
+        assert_eq!(aligned[0]["rows"], "2");
+        assert_eq!(aligned[0]["cols"], "4");
+    }
+}
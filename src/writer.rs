@@ -0,0 +1,707 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::alphabet::Alphabet;
+use crate::msa::MSA;
+use crate::trace::Operation;
+
+/// Default number of residues written per line in interleaved formats
+const BLOCK_WIDTH: usize = 60;
+
+pub struct Writer<W> {
+    inner: W,
+
+    /// Number of residues per line in interleaved formats
+    block_width: usize,
+
+    /// Append cumulative residue numbers to CLUSTAL sequence lines
+    residue_numbers: bool,
+
+    /// Number of residues per FASTA sequence line, 0 for no wrapping
+    line_width: usize,
+
+    /// Write the "description" record annotation on FASTA header lines
+    descriptions: bool,
+}
+
+impl<W> Writer<W>
+where
+    W: Write,
+{
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            block_width: BLOCK_WIDTH,
+            residue_numbers: false,
+            line_width: BLOCK_WIDTH,
+            descriptions: true,
+        }
+    }
+
+    /// Set the number of residues per line in interleaved output (default 60)
+    pub fn block_width(mut self, width: usize) -> Self {
+        self.block_width = width.max(1);
+        self
+    }
+
+    /// Append the cumulative residue count to each CLUSTAL sequence line,
+    /// as ClustalW does with `-SEQNOS=ON`
+    pub fn residue_numbers(mut self, numbers: bool) -> Self {
+        self.residue_numbers = numbers;
+        self
+    }
+
+    /// Set the number of residues per FASTA sequence line (default 60), 0
+    /// writing every sequence on a single line
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width;
+        self
+    }
+
+    /// Write the "description" annotation of each record after its ID on
+    /// FASTA header lines (default `true`)
+    pub fn descriptions(mut self, descriptions: bool) -> Self {
+        self.descriptions = descriptions;
+        self
+    }
+
+    pub fn write_clustal(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_clustal");
+        write_clustal(&mut self.inner, msa, self.block_width, self.residue_numbers)?;
+        op.finish(msa);
+        Ok(())
+    }
+
+    /// Write the alignment in non-interleaved Stockholm format. Alignment
+    /// annotations become `#=GF` lines and column annotations `#=GC` lines.
+    /// Record annotations as long as the sequence become `#=GR` lines, the
+    /// others `#=GS` lines. Multi-line values are written one line each.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if an ID or annotation
+    /// name is empty or contains whitespace.
+    pub fn write_stockholm(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_stockholm");
+        write_stockholm(&mut self.inner, msa)?;
+        op.finish(msa);
+        Ok(())
+    }
+
+    /// Write the alignment in strict sequential PHYLIP format: the number of
+    /// records and columns, then each record on one line, its name truncated
+    /// or padded to 10 characters followed by its sequence.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`], before writing anything,
+    /// if a name is empty or if two names are the same once truncated.
+    pub fn write_phylip(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_phylip");
+        write_phylip(&mut self.inner, msa, true)?;
+        op.finish(msa);
+        Ok(())
+    }
+
+    /// Write the alignment in relaxed sequential PHYLIP format, where names
+    /// are not truncated and end at the first whitespace. Sequences all start
+    /// on the same column.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`], before writing anything,
+    /// if a name is empty or contains whitespace.
+    pub fn write_phylip_relaxed(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_phylip_relaxed");
+        write_phylip(&mut self.inner, msa, false)?;
+        op.finish(msa);
+        Ok(())
+    }
+
+    /// Write the alignment as a NEXUS file with a single non-interleaved
+    /// `DATA` block. The "datatype", "gap" and "missing" annotations are
+    /// used for the `FORMAT` command, defaulting to the guessed alphabet,
+    /// `-` and `?`. Names that are not plain NEXUS words are quoted.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`], before writing anything,
+    /// if a name is empty or not unique.
+    pub fn write_nexus(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_nexus");
+        write_nexus(&mut self.inner, msa)?;
+        op.finish(msa);
+        Ok(())
+    }
+
+    /// Write the alignment as aligned FASTA. Nothing is written for an
+    /// alignment without records.
+    pub fn write_fasta(&mut self, msa: &MSA) -> io::Result<()> {
+        let op = Operation::start("write_fasta");
+        write_fasta(&mut self.inner, msa, self.line_width, self.descriptions)?;
+        op.finish(msa);
+        Ok(())
+    }
+}
+
+fn write_clustal<W>(
+    writer: &mut W,
+    msa: &MSA,
+    block_width: usize,
+    residue_numbers: bool,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    // Header line
+    let program = msa
+        .get_annotation("program")
+        .map(|x| x.as_str())
+        .unwrap_or("CLUSTAL");
+    match msa.get_annotation("version") {
+        Some(version) => writeln!(
+            writer,
+            "{} ({}) multiple sequence alignment",
+            program, version
+        )?,
+        None => writeln!(writer, "{} multiple sequence alignment", program)?,
+    }
+    writeln!(writer)?;
+
+    // Identifiers are padded so that every sequence starts on the same column
+    let id_width = msa
+        .records()
+        .iter()
+        .map(|x| x.id().len())
+        .max()
+        .unwrap_or(0)
+        + 6;
+    let cons = msa.get_column_annotation("cons");
+    let mut counts = vec![0; msa.len()];
+
+    let mut start = 0;
+    while start < msa.col_len() {
+        let end = std::cmp::min(start + block_width, msa.col_len());
+        writeln!(writer)?;
+        for (record, count) in msa.records().iter().zip(counts.iter_mut()) {
+            let sequence = record.sequence().get(start..end).unwrap_or("");
+            write!(
+                writer,
+                "{:<width$}{}",
+                record.id(),
+                sequence,
+                width = id_width
+            )?;
+
+            // Like ClustalW, no number is printed for an all-gap segment
+            let residues = sequence.bytes().filter(|x| !b"-.".contains(x)).count();
+            *count += residues;
+            if residue_numbers && residues > 0 {
+                write!(writer, " {}", count)?;
+            }
+            writeln!(writer)?;
+        }
+        if let Some(cons) = cons {
+            // Keep the conservation line as wide as the block so it can be read back
+            writeln!(
+                writer,
+                "{:<width$}{:<block$}",
+                "",
+                cons.get(start..end).unwrap_or(""),
+                width = id_width,
+                block = end - start
+            )?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+fn write_fasta<W>(
+    writer: &mut W,
+    msa: &MSA,
+    line_width: usize,
+    descriptions: bool,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    for record in msa.records() {
+        match record.get_annotation("description") {
+            Some(description) if descriptions => {
+                writeln!(writer, ">{} {}", record.id(), description)?
+            }
+            _ => writeln!(writer, ">{}", record.id())?,
+        }
+        let sequence = record.sequence().as_bytes();
+        let width = if line_width == 0 {
+            sequence.len().max(1)
+        } else {
+            line_width
+        };
+        for line in sequence.chunks(width) {
+            writer.write_all(line)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Width of the name field in strict PHYLIP
+const PHYLIP_NAME_WIDTH: usize = 10;
+
+fn write_phylip<W>(writer: &mut W, msa: &MSA, strict: bool) -> io::Result<()>
+where
+    W: Write,
+{
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+    // Check every name before writing anything
+    let mut names: Vec<String> = Vec::with_capacity(msa.len());
+    let mut seen = HashSet::new();
+    for record in msa.records() {
+        let name = match strict {
+            true => record.id().chars().take(PHYLIP_NAME_WIDTH).collect(),
+            false => record.id().to_string(),
+        };
+        if name.trim().is_empty() || (!strict && name.contains(char::is_whitespace)) {
+            return Err(invalid(format!("invalid PHYLIP name {:?}", record.id())));
+        }
+        if !seen.insert(name.trim().to_string()) {
+            return Err(invalid(format!(
+                "PHYLIP name {:?} of {} is not unique",
+                name.trim(),
+                record.id()
+            )));
+        }
+        names.push(name);
+    }
+
+    let width = match strict {
+        true => PHYLIP_NAME_WIDTH,
+        false => names.iter().map(|x| x.chars().count()).max().unwrap_or(0) + 1,
+    };
+    writeln!(writer, "{} {}", msa.len(), msa.col_len())?;
+    for (name, record) in names.iter().zip(msa.records()) {
+        writeln!(
+            writer,
+            "{:<width$}{}",
+            name,
+            record.sequence(),
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+fn write_nexus<W>(writer: &mut W, msa: &MSA) -> io::Result<()>
+where
+    W: Write,
+{
+    // Check every name before writing anything
+    let mut names: Vec<String> = Vec::with_capacity(msa.len());
+    let mut seen = HashSet::new();
+    for record in msa.records() {
+        if record.id().is_empty() || !seen.insert(record.id()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid or repeated NEXUS name {:?}", record.id()),
+            ));
+        }
+        names.push(nexus_name(record.id()));
+    }
+
+    let format = |name: &str, default: &str| -> String {
+        msa.get_annotation(name)
+            .cloned()
+            .unwrap_or_else(|| default.to_string())
+    };
+    let datatype = match msa.guess_alphabet() {
+        Alphabet::Dna => "DNA",
+        Alphabet::Rna => "RNA",
+        Alphabet::Protein => "PROTEIN",
+        Alphabet::Unknown => "STANDARD",
+    };
+    writeln!(writer, "#NEXUS")?;
+    writeln!(writer, "BEGIN DATA;")?;
+    writeln!(
+        writer,
+        "\tDIMENSIONS NTAX={} NCHAR={};",
+        msa.len(),
+        msa.col_len()
+    )?;
+    writeln!(
+        writer,
+        "\tFORMAT DATATYPE={} MISSING={} GAP={};",
+        format("datatype", datatype),
+        format("missing", "?"),
+        format("gap", "-")
+    )?;
+    writeln!(writer, "\tMATRIX")?;
+    let width = names.iter().map(|x| x.chars().count()).max().unwrap_or(0) + 1;
+    for (name, record) in names.iter().zip(msa.records()) {
+        writeln!(
+            writer,
+            "\t{:<width$}{}",
+            name,
+            record.sequence(),
+            width = width
+        )?;
+    }
+    writeln!(writer, "\t;")?;
+    writeln!(writer, "END;")?;
+    Ok(())
+}
+
+/// Quote a NEXUS name holding whitespace or punctuation
+fn nexus_name(name: &str) -> String {
+    if name.contains(|c: char| c.is_whitespace() || "()[]{}/\\,;:=*'\"`+-<>".contains(c)) {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+fn write_stockholm<W>(writer: &mut W, msa: &MSA) -> io::Result<()>
+where
+    W: Write,
+{
+    let check = |name: &str| -> io::Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid Stockholm name {:?}", name),
+            ));
+        }
+        Ok(())
+    };
+    fn sorted<'a>(map: impl Iterator<Item = (&'a String, &'a String)>) -> Vec<(String, String)> {
+        let mut entries: Vec<_> = map.map(|(k, v)| (k.clone(), v.clone())).collect();
+        entries.sort();
+        entries
+    }
+
+    // Check every name before writing anything
+    let annotations = sorted(msa.annotations());
+    let column_annotations = sorted(msa.column_annotations());
+    for (name, _) in annotations.iter().chain(&column_annotations) {
+        check(name)?;
+    }
+    let mut records = Vec::with_capacity(msa.len());
+    for record in msa.records() {
+        check(record.id())?;
+        let (per_residue, per_record): (Vec<_>, Vec<_>) = sorted(record.annotations().iter())
+            .into_iter()
+            .partition(|(_, value)| value.len() == record.len());
+        for (name, _) in per_residue.iter().chain(&per_record) {
+            check(name)?;
+        }
+        records.push((record, per_residue, per_record));
+    }
+
+    // Sequences, #=GR and #=GC values all start on the same column
+    let labels = msa
+        .records()
+        .iter()
+        .map(|x| x.id().len())
+        .chain(records.iter().flat_map(|(r, gr, _)| {
+            gr.iter()
+                .map(move |(name, _)| r.id().len() + name.len() + 6)
+        }))
+        .chain(column_annotations.iter().map(|(name, _)| name.len() + 5));
+    let width = labels.max().unwrap_or(0) + 1;
+
+    writeln!(writer, "# STOCKHOLM 1.0")?;
+    for (name, value) in &annotations {
+        for line in value.lines() {
+            writeln!(writer, "#=GF {} {}", name, line)?;
+        }
+    }
+    for (record, _, per_record) in &records {
+        for (name, value) in per_record {
+            for line in value.lines() {
+                writeln!(writer, "#=GS {} {} {}", record.id(), name, line)?;
+            }
+        }
+    }
+    writeln!(writer)?;
+    for (record, per_residue, _) in &records {
+        writeln!(
+            writer,
+            "{:<width$}{}",
+            record.id(),
+            record.sequence(),
+            width = width
+        )?;
+        for (name, value) in per_residue {
+            let label = format!("#=GR {} {}", record.id(), name);
+            writeln!(writer, "{:<width$}{}", label, value, width = width)?;
+        }
+    }
+    for (name, value) in &column_annotations {
+        let label = format!("#=GC {}", name);
+        writeln!(writer, "{:<width$}{}", label, value, width = width)?;
+    }
+    writeln!(writer, "//")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+    use crate::reader::Reader;
+
+    fn write_to_string(msa: &MSA) -> String {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_clustal(msa).unwrap();
+        String::from_utf8(writer.inner).unwrap()
+    }
+
+    #[test]
+    fn test_clustal_round_trip() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/clustalw.aln").unwrap()));
+        let msa = data.read_clustal().unwrap();
+
+        let output = write_to_string(&msa);
+        assert!(output.starts_with("CLUSTAL (1.81) multiple sequence alignment\n"));
+
+        let parsed = Reader::new(output.as_bytes()).read_clustal().unwrap();
+        assert_eq!(parsed, msa);
+    }
+
+    #[test]
+    fn test_clustal_identical_blocks() {
+        let cases = [
+            ("tests/muscle.aln", Writer::new(Vec::new())),
+            (
+                "tests/clustalw.aln",
+                Writer::new(Vec::new())
+                    .block_width(50)
+                    .residue_numbers(true),
+            ),
+            (
+                "tests/odd_consensus.aln",
+                Writer::new(Vec::new()).residue_numbers(true),
+            ),
+        ];
+        for (path, mut writer) in cases {
+            let input = std::fs::read_to_string(path).unwrap();
+            let msa = Reader::new(input.as_bytes()).read_clustal().unwrap();
+            writer.write_clustal(&msa).unwrap();
+            let output = String::from_utf8(writer.inner).unwrap();
+
+            // Everything but the header line must match byte for byte
+            let blocks = |s: &str| s.split_once('\n').unwrap().1.to_string();
+            assert_eq!(blocks(&output), blocks(&input), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_fasta_golden() {
+        let mut msa = MSA::default();
+        msa.push_record("seq1", "ACGTAC-T");
+        msa.push_record("seq2", "AC--ACGT");
+        msa.push_record_annotation("seq1", "description", "first sequence");
+
+        let mut writer = Writer::new(Vec::new()).line_width(3);
+        writer.write_fasta(&msa).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.inner).unwrap(),
+            ">seq1 first sequence\nACG\nTAC\n-T\n>seq2\nAC-\n-AC\nGT\n"
+        );
+
+        let mut writer = Writer::new(Vec::new()).line_width(0).descriptions(false);
+        writer.write_fasta(&msa).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.inner).unwrap(),
+            ">seq1\nACGTAC-T\n>seq2\nAC--ACGT\n"
+        );
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_fasta(&MSA::default()).unwrap();
+        assert!(writer.inner.is_empty());
+    }
+
+    #[test]
+    fn test_fasta_round_trip() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/clustalw.aln").unwrap()));
+        let msa = data.read_clustal().unwrap();
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_fasta(&msa).unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        assert!(output.lines().all(|x| x.len() <= 60));
+
+        let parsed = Reader::new(output.as_bytes()).read_fasta().unwrap();
+        assert_eq!(parsed.records(), msa.records());
+    }
+
+    #[test]
+    fn test_fasta_fasta_round_trip() {
+        let input = format!(
+            ">seq1 first sequence, from a | pipeline\n{}\n{}\n>seq2\n{}\n{}\n",
+            "AC-T".repeat(15),
+            "AC",
+            "-".repeat(60),
+            "GT"
+        );
+        let msa = Reader::new(input.as_bytes()).read_fasta().unwrap();
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_fasta(&msa).unwrap();
+        assert_eq!(String::from_utf8(writer.inner).unwrap(), input);
+
+        let mut data = Reader::new(BufReader::new(File::open("tests/clustalw.fa").unwrap()));
+        let msa = data.read_fasta().unwrap();
+        let mut writer = Writer::new(Vec::new()).line_width(100);
+        writer.write_fasta(&msa).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.inner).unwrap(),
+            std::fs::read_to_string("tests/clustalw.fa").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_phylip_golden() {
+        let mut msa = MSA::default();
+        msa.push_record("seq1", "ACGT-A");
+        msa.push_record("a_long_name_1", "AC-TTA");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_phylip(&msa).unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        assert_eq!(output, "2 6\nseq1      ACGT-A\na_long_namAC-TTA\n");
+        let parsed = Reader::new(output.as_bytes()).read_phylip().unwrap();
+        assert_eq!(parsed.get(1).unwrap().id(), "a_long_nam");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_phylip_relaxed(&msa).unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        assert_eq!(output, "2 6\nseq1          ACGT-A\na_long_name_1 AC-TTA\n");
+        let parsed = Reader::new(output.as_bytes()).read_phylip().unwrap();
+        assert_eq!(parsed.records(), msa.records());
+    }
+
+    #[test]
+    fn test_phylip_invalid_names() {
+        let mut msa = MSA::default();
+        msa.push_record("a_long_name_1", "ACGT");
+        msa.push_record("a_long_name_2", "ACGA");
+        let mut writer = Writer::new(Vec::new());
+        let err = writer.write_phylip(&msa).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(writer.inner.is_empty());
+        writer.write_phylip_relaxed(&msa).unwrap();
+
+        msa.push_record("with space", "ACGC");
+        let mut writer = Writer::new(Vec::new());
+        let err = writer.write_phylip_relaxed(&msa).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(writer.inner.is_empty());
+    }
+
+    #[test]
+    fn test_nexus_golden() {
+        let mut msa = MSA::default();
+        msa.push_record("seq1", "ACGT-A");
+        msa.push_record("Pongo's cousin", "AC-TTA");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_nexus(&msa).unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        assert_eq!(
+            output,
+            "#NEXUS\nBEGIN DATA;\n\tDIMENSIONS NTAX=2 NCHAR=6;\n\tFORMAT DATATYPE=DNA MISSING=? GAP=-;\n\tMATRIX\n\tseq1              ACGT-A\n\t'Pongo''s cousin' AC-TTA\n\t;\nEND;\n"
+        );
+        let parsed = Reader::new(output.as_bytes()).read_nexus().unwrap();
+        assert_eq!(parsed.records(), msa.records());
+
+        let msa = Reader::new(BufReader::new(File::open("tests/primates.nex").unwrap()))
+            .read_nexus()
+            .unwrap();
+        let mut writer = Writer::new(Vec::new());
+        writer.write_nexus(&msa).unwrap();
+        let parsed = Reader::new(&writer.inner[..]).read_nexus().unwrap();
+        assert_eq!(parsed, msa);
+
+        let mut msa = MSA::default();
+        msa.push_record("", "ACGT");
+        let mut writer = Writer::new(Vec::new());
+        let err = writer.write_nexus(&msa).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(writer.inner.is_empty());
+    }
+
+    #[test]
+    fn test_stockholm_golden() {
+        let mut msa = MSA::default();
+        msa.push_record("seq1", "ACGT-A");
+        msa.push_record("seq2", "AC-TTA");
+        msa.add_annotation("ID".to_string(), "test".to_string());
+        msa.add_annotation("CC".to_string(), "first line\nsecond line".to_string());
+        msa.push_record_annotation("seq1", "SS", "HHHH-E");
+        msa.push_record_annotation("seq2", "AC", "P00001.1");
+        msa.add_column_annotation("SS_cons", "HHHH.E");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_stockholm(&msa).unwrap();
+        let expected = "# STOCKHOLM 1.0\n\
+            #=GF CC first line\n\
+            #=GF CC second line\n\
+            #=GF ID test\n\
+            #=GS seq2 AC P00001.1\n\
+            \n\
+            seq1         ACGT-A\n\
+            #=GR seq1 SS HHHH-E\n\
+            seq2         AC-TTA\n\
+            #=GC SS_cons HHHH.E\n\
+            //\n";
+        assert_eq!(String::from_utf8(writer.inner).unwrap(), expected);
+
+        msa.add_column_annotation("bad name", "......");
+        let mut writer = Writer::new(Vec::new());
+        let err = writer.write_stockholm(&msa).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(writer.inner.is_empty());
+    }
+
+    #[test]
+    fn test_stockholm_round_trip() {
+        let mut data = Reader::new(BufReader::new(File::open("tests/pfam.sto").unwrap()));
+        let msa = data.read_stockholm().unwrap();
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_stockholm(&msa).unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        let parsed = Reader::new(output.as_bytes()).read_stockholm().unwrap();
+        assert_eq!(parsed, msa);
+    }
+
+    #[test]
+    fn test_clustal_default_header() {
+        let mut msa = MSA::default();
+        msa.push_record("id1", "ACGT");
+        let output = write_to_string(&msa);
+        assert_eq!(
+            output,
+            "CLUSTAL multiple sequence alignment\n\n\nid1      ACGT\n"
+        );
+    }
+
+    #[test]
+    fn test_clustal_partial_block_and_long_ids() {
+        let mut msa = MSA::default();
+        let long_id = "a_very_long_identifier_exceeding_the_usual_field";
+        msa.push_record(long_id, &"A".repeat(75));
+        msa.push_record("short", &"C".repeat(75));
+        msa.add_column_annotation("cons", &" ".repeat(75));
+
+        let output = write_to_string(&msa);
+        let lines: Vec<&str> = output.lines().collect();
+        let seq_start = long_id.len() + 6;
+        assert_eq!(lines[3].len(), seq_start + 60);
+        assert_eq!(&lines[4][seq_start..], "C".repeat(60));
+        assert_eq!(lines[8].len(), seq_start + 15);
+        assert_eq!(&lines[9][..seq_start], " ".repeat(seq_start));
+
+        let parsed = Reader::new(output.as_bytes()).read_clustal().unwrap();
+        assert_eq!(parsed.records(), msa.records());
+        assert_eq!(
+            parsed.get_column_annotation("cons"),
+            msa.get_column_annotation("cons")
+        );
+    }
+}